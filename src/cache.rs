@@ -0,0 +1,295 @@
+use crate::sizing::{DirSize, DirStamp, LinkedFile, SizeOptions, SymlinkPolicy};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Bump whenever the on-disk layout changes; older files are discarded
+const CACHE_VERSION: u32 = 4;
+
+/// A previously computed directory size, with the stamps needed to revalidate it
+///
+/// A tree is considered unchanged when every directory it contained still has
+/// the same inode and mtime. Adding, removing or renaming an entry always bumps
+/// the parent directory's mtime, so this catches everything package managers
+/// and build tools do, without touching a single file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CacheEntry {
+    /// Options the size was computed with; sizes taken with others don't match
+    sized_with: SizedWith,
+    apparent: u64,
+    allocated: u64,
+    linked: Vec<(u64, u64, LinkedFile)>,
+    /// Directory stamps, with paths relative to the cached directory
+    dirs: Vec<DirStamp>,
+    newest_mtime_ns: u64,
+}
+
+/// The sizing options a directory's size depends on
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct SizedWith {
+    one_file_system: bool,
+    symlinks: SymlinkPolicy,
+    /// Scan roots, which decide what `SymlinkPolicy::WithinRoot` follows
+    /// (empty with other policies)
+    roots: Vec<PathBuf>,
+}
+
+impl SizedWith {
+    fn new(options: &SizeOptions) -> SizedWith {
+        SizedWith {
+            one_file_system: options.one_file_system,
+            symlinks: options.symlinks,
+            roots: match options.symlinks {
+                SymlinkPolicy::WithinRoot => options.roots.clone(),
+                _ => Vec::new(),
+            },
+        }
+    }
+}
+
+impl CacheEntry {
+    fn from_size(root: &Path, options: &SizeOptions, size: &DirSize) -> Option<CacheEntry> {
+        // An incomplete size must be recomputed next time
+        if !size.errors.is_empty() {
+            return None;
+        }
+        let sized_with = SizedWith::new(options);
+        if sized_with.roots.iter().any(|root| root.to_str().is_none()) {
+            return None;
+        }
+
+        let dirs = size
+            .dirs
+            .iter()
+            .map(|stamp| {
+                let relative = stamp.path.strip_prefix(root).ok()?;
+                // Non UTF-8 paths can't round-trip through JSON
+                relative.to_str()?;
+                Some(DirStamp {
+                    path: relative.to_path_buf(),
+                    ..stamp.clone()
+                })
+            })
+            .collect::<Option<Vec<_>>>()?;
+
+        Some(CacheEntry {
+            sized_with,
+            apparent: size.apparent,
+            allocated: size.allocated,
+            linked: size
+                .linked
+                .iter()
+                .map(|(&(dev, ino), &file)| (dev, ino, file))
+                .collect(),
+            dirs,
+            newest_mtime_ns: size.newest_mtime_ns,
+        })
+    }
+
+    /// Check every recorded directory against the filesystem
+    fn is_fresh(&self, root: &Path) -> bool {
+        !self.dirs.is_empty()
+            && self.dirs.par_iter().all(|stamp| {
+                let path = root.join(&stamp.path);
+                DirStamp::read(&path).is_some_and(|current| {
+                    current.mtime_ns == stamp.mtime_ns && current.inode == stamp.inode
+                })
+            })
+    }
+
+    fn to_size(&self, root: &Path) -> DirSize {
+        DirSize {
+            apparent: self.apparent,
+            allocated: self.allocated,
+            linked: self
+                .linked
+                .iter()
+                .map(|&(dev, ino, file)| ((dev, ino), file))
+                .collect(),
+            dirs: self
+                .dirs
+                .iter()
+                .map(|stamp| DirStamp {
+                    path: root.join(&stamp.path),
+                    ..stamp.clone()
+                })
+                .collect(),
+            newest_mtime_ns: self.newest_mtime_ns,
+            // Trees that reach outside through symlinks are never cached
+            via_symlinks: 0,
+            errors: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct CacheFile {
+    version: u32,
+    entries: HashMap<PathBuf, CacheEntry>,
+}
+
+/// Borrowed counterpart of `CacheFile`, to save without cloning every entry
+#[derive(Serialize)]
+struct CacheFileRef<'a> {
+    version: u32,
+    entries: &'a HashMap<PathBuf, CacheEntry>,
+}
+
+/// Persistent cache of directory sizes, shared across runs
+pub struct SizeCache {
+    file: PathBuf,
+    /// Keyed by canonical path, so every working directory shares entries
+    entries: Mutex<HashMap<PathBuf, CacheEntry>>,
+}
+
+impl SizeCache {
+    /// Location of the cache file (under the XDG cache dir on Linux)
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::cache_dir().map(|dir| dir.join("sweepkit").join("sizes.json"))
+    }
+
+    /// Load the cache, starting empty if it is missing, unreadable or outdated
+    pub fn load(file: PathBuf) -> SizeCache {
+        let entries = fs::read(&file)
+            .ok()
+            .and_then(|data| serde_json::from_slice::<CacheFile>(&data).ok())
+            .filter(|cache| cache.version == CACHE_VERSION)
+            .map(|cache| cache.entries)
+            .unwrap_or_default();
+
+        SizeCache {
+            file,
+            entries: Mutex::new(entries),
+        }
+    }
+
+    /// Return the cached size of a directory if its tree is unchanged and it
+    /// was sized with the same options
+    pub fn lookup(&self, path: &Path, options: &SizeOptions) -> Option<DirSize> {
+        let key = fs::canonicalize(path).ok()?;
+        let entry = self.entries.lock().ok()?.get(&key).cloned()?;
+        if entry.sized_with == SizedWith::new(options) && entry.is_fresh(&key) {
+            Some(entry.to_size(path))
+        } else {
+            None
+        }
+    }
+
+    /// Remember the size of a directory, as computed with `options`
+    pub fn store(&self, path: &Path, options: &SizeOptions, size: &DirSize) {
+        let Some(entry) = CacheEntry::from_size(path, options, size) else {
+            return;
+        };
+        let Ok(key) = fs::canonicalize(path) else {
+            return;
+        };
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.insert(key, entry);
+        }
+    }
+
+    /// Drop entries whose directory is gone or has changed since it was sized
+    ///
+    /// Returns the number of entries removed.
+    pub fn prune(&self) -> usize {
+        let Ok(mut entries) = self.entries.lock() else {
+            return 0;
+        };
+
+        let before = entries.len();
+        let stale: Vec<PathBuf> = entries
+            .par_iter()
+            .filter(|(path, entry)| !entry.is_fresh(path))
+            .map(|(path, _)| path.clone())
+            .collect();
+        for path in stale {
+            entries.remove(&path);
+        }
+        before - entries.len()
+    }
+
+    /// Number of cached directories
+    pub fn len(&self) -> usize {
+        self.entries
+            .lock()
+            .map(|entries| entries.len())
+            .unwrap_or(0)
+    }
+
+    /// Write the cache back to disk
+    pub fn save(&self) -> io::Result<()> {
+        let entries = self
+            .entries
+            .lock()
+            .map_err(|_| io::Error::new(io::ErrorKind::Other, "size cache lock poisoned"))?;
+        let cache = CacheFileRef {
+            version: CACHE_VERSION,
+            entries: &entries,
+        };
+        let data = serde_json::to_vec(&cache)?;
+
+        if let Some(parent) = self.file.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        // Write then rename, so a concurrent run never reads a partial file
+        let tmp = self
+            .file
+            .with_extension(format!("tmp.{}", std::process::id()));
+        fs::write(&tmp, data)?;
+        fs::rename(&tmp, &self.file)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sizing;
+    use crate::testing::test_dir;
+
+    #[test]
+    fn sizes_are_reused_until_the_tree_or_options_change() {
+        let root = test_dir("cache");
+        let dir = root.join("node_modules");
+        fs::create_dir_all(dir.join("pkg")).unwrap();
+        fs::write(dir.join("pkg/index.js"), "module.exports = 1;\n").unwrap();
+        // So the change below can't fall in the same clock tick
+        #[cfg(unix)]
+        crate::testing::backdate_dirs(&dir, 1);
+
+        let cache = SizeCache::load(root.join("sizes.json"));
+        let never = SizeOptions::default();
+        let always = SizeOptions {
+            symlinks: SymlinkPolicy::Always,
+            ..SizeOptions::default()
+        };
+        let sized = sizing::calculate_dir_size(&dir, &never);
+        cache.store(&dir, &never, &sized);
+
+        let cached = cache.lookup(&dir, &never).unwrap();
+        assert_eq!(cached.apparent_size(), sized.apparent_size());
+        assert_eq!(cached.reclaimable_size(), sized.reclaimable_size());
+        // Also under another spelling of the path
+        assert!(cache
+            .lookup(&root.join("node_modules/pkg/.."), &never)
+            .is_some());
+
+        // Links are followed under another policy: the cached size can't tell
+        assert!(cache.lookup(&dir, &always).is_none());
+        let one_file_system = SizeOptions {
+            one_file_system: true,
+            ..SizeOptions::default()
+        };
+        assert!(cache.lookup(&dir, &one_file_system).is_none());
+
+        // A new entry anywhere in the tree bumps a directory's mtime
+        fs::write(dir.join("pkg/extra.js"), "").unwrap();
+        assert!(cache.lookup(&dir, &never).is_none());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,137 @@
+use crate::git::GitStatus;
+use crate::languages::ArtifactKind;
+use crate::scanner::FoundItem;
+use crate::sizing::ScanError;
+use colored::Colorize;
+use serde::Serialize;
+
+/// How `sweepkit check` reports its findings
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum OutputFormat {
+    /// Human-readable list
+    #[default]
+    Text,
+    /// A JSON document on stdout, for CI annotations
+    Json,
+}
+
+/// An artifact directory with files in the git index
+#[derive(Debug, Serialize)]
+pub struct TrackedArtifact {
+    pub path: String,
+    pub label: String,
+    pub kind: ArtifactKind,
+    pub tracked_files: usize,
+}
+
+#[derive(Serialize)]
+struct Report<'a> {
+    tracked: &'a [TrackedArtifact],
+    /// Artifacts in repositories git couldn't be asked about
+    unknown: &'a [String],
+    /// Entries that could not be read; some artifacts may have been missed
+    unreadable: Vec<String>,
+}
+
+/// Artifact directories that are committed to git
+///
+/// Ambiguous matches (`dist`, `build`, ...) are often committed on purpose
+/// and only count with `include_uncertain`.
+pub fn tracked_artifacts(items: &[FoundItem], include_uncertain: bool) -> Vec<TrackedArtifact> {
+    items
+        .iter()
+        .filter(|item| include_uncertain || !item.ambiguous)
+        .filter_map(|item| match item.git {
+            GitStatus::Tracked(count) => Some(TrackedArtifact {
+                path: item.path.to_string_lossy().into_owned(),
+                label: item.label.clone(),
+                kind: item.kind,
+                tracked_files: count,
+            }),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Artifact directories git couldn't tell about (not installed, repository
+/// of another user, ...), which must fail the check too
+pub fn unknown_artifacts(items: &[FoundItem], include_uncertain: bool) -> Vec<String> {
+    items
+        .iter()
+        .filter(|item| include_uncertain || !item.ambiguous)
+        .filter(|item| item.git == GitStatus::Unknown)
+        .map(|item| item.path.to_string_lossy().into_owned())
+        .collect()
+}
+
+/// Print the findings of a check in the requested format
+pub fn report(
+    tracked: &[TrackedArtifact],
+    unknown: &[String],
+    errors: &[ScanError],
+    format: OutputFormat,
+) {
+    match format {
+        OutputFormat::Json => {
+            let report = Report {
+                tracked,
+                unknown,
+                unreadable: errors.iter().map(|e| e.to_string()).collect(),
+            };
+            match serde_json::to_string_pretty(&report) {
+                Ok(json) => println!("{}", json),
+                Err(e) => eprintln!("❌ Failed to write report: {}", e),
+            }
+        }
+        OutputFormat::Text if tracked.is_empty() && unknown.is_empty() => {
+            println!("✅ No artifact directories are tracked by git");
+        }
+        OutputFormat::Text => {
+            if !unknown.is_empty() {
+                println!(
+                    "{}",
+                    format!(
+                        "❌ git couldn't tell whether {} artifact directories are tracked:",
+                        unknown.len()
+                    )
+                    .bold()
+                );
+                for path in unknown {
+                    println!("  {}", path);
+                }
+                if tracked.is_empty() {
+                    return;
+                }
+                println!();
+            }
+
+            println!(
+                "{}",
+                format!(
+                    "❌ {} artifact directories are committed to git:",
+                    tracked.len()
+                )
+                .bold()
+            );
+            for artifact in tracked {
+                let files = if artifact.tracked_files == 1 {
+                    "1 tracked file".to_string()
+                } else {
+                    format!("{} tracked files", artifact.tracked_files)
+                };
+                println!(
+                    "  {} ({}, {}) - {}",
+                    artifact.path,
+                    artifact.label,
+                    artifact.kind,
+                    files.red()
+                );
+            }
+            println!(
+                "\n{}",
+                "ℹ️  Untrack them with `git rm -r --cached <path>` and add them to .gitignore"
+                    .dimmed()
+            );
+        }
+    }
+}
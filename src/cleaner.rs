@@ -1,40 +1,158 @@
+use crate::confidence::Confidence;
+use crate::deletion;
+use crate::git::GitStatus;
+use crate::quarantine;
 use crate::scanner::FoundItem;
+use crate::trash;
 use crate::utils;
 use colored::Colorize;
 use dialoguer::{theme::ColorfulTheme, Confirm, MultiSelect};
 use indicatif::{ProgressBar, ProgressStyle};
-use std::fs;
+use rayon::prelude::*;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::Instant;
 
-/// Delete a directory with all its contents
-fn delete_directory(path: &std::path::Path) -> Result<(), std::io::Error> {
-    fs::remove_dir_all(path)
+/// What happens to the directories selected for cleaning
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Removal {
+    /// Delete them for good, making read-only directories the user owns
+    /// writable first if `fix_permissions` is set
+    Delete { fix_permissions: bool },
+    /// Move them to the freedesktop.org Trash
+    Trash,
+    /// Move them to sweepkit's quarantine, from where they can be restored
+    Quarantine,
 }
 
-/// Display directories and allow interactive selection for deletion
-pub fn clean_directories(items: Vec<FoundItem>, all: bool) {
+/// A `Removal` under way, shared by the deletion workers
+enum Remover {
+    Delete { fix_permissions: bool },
+    Trash,
+    Quarantine(Mutex<quarantine::Run>),
+}
+
+impl Remover {
+    fn start(removal: Removal) -> Result<Remover, std::io::Error> {
+        Ok(match removal {
+            Removal::Delete { fix_permissions } => Remover::Delete { fix_permissions },
+            Removal::Trash => Remover::Trash,
+            Removal::Quarantine => Remover::Quarantine(Mutex::new(quarantine::Run::start()?)),
+        })
+    }
+
+    /// Remove one target; `removed` is told about bytes freed along the way
+    fn remove(&self, target: &Target, removed: &dyn Fn(u64)) -> Result<(), std::io::Error> {
+        match self {
+            Remover::Delete { fix_permissions } => {
+                deletion::delete_directory(&target.path, *fix_permissions, removed)
+            }
+            Remover::Trash => trash::trash(&target.path).map(|_| ()),
+            Remover::Quarantine(run) => match run.lock() {
+                Ok(mut run) => run.quarantine(&target.path, target.size),
+                Err(_) => Err(std::io::Error::new(
+                    std::io::ErrorKind::Other,
+                    "quarantine journal unavailable",
+                )),
+            },
+        }
+    }
+}
+
+/// Options for cleaning
+#[derive(Debug, Clone, Copy)]
+pub struct CleanOptions {
+    /// Clean everything without asking
+    pub all: bool,
+    /// With `all`, also clean low-confidence items
+    pub include_uncertain: bool,
+    pub removal: Removal,
+    /// Directories removed at once (0 for one per CPU)
+    pub jobs: usize,
+}
+
+/// A directory to remove
+#[derive(Debug, Clone)]
+pub struct Target {
+    pub path: PathBuf,
+    /// Bytes removing it frees
+    pub size: u64,
+}
+
+/// Display directories and let the user pick some
+///
+/// Directories containing files tracked by git, or whose git status is
+/// unknown, are never offered. With `all`,
+/// every other one is picked, except low-confidence items unless
+/// `include_uncertain` is set. Returns nothing if the selection is cancelled.
+pub fn select_items(items: Vec<FoundItem>, all: bool, include_uncertain: bool) -> Vec<FoundItem> {
     if items.is_empty() {
         println!("✨ No dev dependency folders found!");
-        return;
+        return Vec::new();
     }
 
     // Display summary
     utils::display_scan_results(&items);
     println!();
 
+    // Committed content can't be regenerated: never offer it for deletion,
+    // nor anything git couldn't tell about
+    let (tracked, items): (Vec<FoundItem>, Vec<FoundItem>) = items
+        .into_iter()
+        .partition(|item| matches!(item.git, GitStatus::Tracked(_)));
+    let (unknown, items): (Vec<FoundItem>, Vec<FoundItem>) = items
+        .into_iter()
+        .partition(|item| item.git == GitStatus::Unknown);
+    for (refused, reason) in [
+        (&tracked, "containing files tracked by git"),
+        (&unknown, "in git repositories git couldn't be asked about"),
+    ] {
+        if refused.is_empty() {
+            continue;
+        }
+        println!(
+            "🛑 Refusing to delete {} directories {}:",
+            refused.len(),
+            reason
+        );
+        for item in refused {
+            println!("   {} ({})", item.path.display(), item.git);
+        }
+        println!();
+    }
+    if items.is_empty() {
+        println!("✨ Nothing left to clean");
+        return Vec::new();
+    }
+
     let selections = if all {
-        // Select all items
-        (0..items.len()).collect::<Vec<_>>()
+        let (selections, uncertain): (Vec<usize>, Vec<usize>) = (0..items.len())
+            .partition(|&i| include_uncertain || items[i].confidence > Confidence::Low);
+        if !uncertain.is_empty() {
+            println!(
+                "⏭️  Skipping {} low-confidence directories (pass --include-uncertain to delete them too)",
+                uncertain.len()
+            );
+        }
+        selections
     } else {
         // Interactive selection
         let item_labels: Vec<String> = items
             .iter()
             .map(|item| {
+                let warning = if item.confidence == Confidence::Low {
+                    " [low confidence]"
+                } else {
+                    ""
+                };
                 format!(
-                    "{} {} - {} ({})",
+                    "{} {} - {} ({}){}",
                     item.icon,
                     item.path.display(),
-                    item.ecosystem,
-                    utils::format_size(item.size)
+                    item.label,
+                    utils::format_size(item.reclaimable),
+                    warning
                 )
             })
             .collect();
@@ -47,18 +165,38 @@ pub fn clean_directories(items: Vec<FoundItem>, all: bool) {
             Ok(selections) => selections,
             Err(_) => {
                 println!("❌ Selection cancelled");
-                return;
+                return Vec::new();
             }
         }
     };
 
     if selections.is_empty() {
         println!("✨ No directories selected for deletion");
+    }
+    items
+        .into_iter()
+        .enumerate()
+        .filter(|(idx, _)| selections.contains(idx))
+        .map(|(_, item)| item)
+        .collect()
+}
+
+/// Display directories and allow interactive selection for deletion
+pub fn clean_directories(items: Vec<FoundItem>, options: CleanOptions) {
+    let CleanOptions {
+        all,
+        include_uncertain,
+        removal,
+        jobs,
+    } = options;
+
+    let selected = select_items(items, all, include_uncertain);
+    if selected.is_empty() {
         return;
     }
 
     // Calculate total space to reclaim
-    let total_size: u64 = selections.iter().map(|&i| items[i].size).sum();
+    let total_size: u64 = selected.iter().map(|item| item.reclaimable).sum();
 
     println!(
         "\n💾 Total space to be reclaimed: {}",
@@ -66,67 +204,184 @@ pub fn clean_directories(items: Vec<FoundItem>, all: bool) {
     );
 
     // Final confirmation
-    if !all {
-        let confirm = Confirm::with_theme(&ColorfulTheme::default())
-            .with_prompt(format!(
-                "Are you sure you want to delete {} directories?",
-                selections.len()
-            ))
-            .default(false)
-            .interact();
-
-        match confirm {
-            Ok(true) => {}
-            _ => {
-                println!("❌ Deletion cancelled");
-                return;
-            }
-        }
+    if !all && !confirm_deletion(selected.len()) {
+        return;
     }
 
+    let targets: Vec<Target> = selected
+        .iter()
+        .map(|item| Target {
+            path: item.path.clone(),
+            size: item.reclaimable,
+        })
+        .collect();
+    remove_directories(&targets, removal, jobs);
+}
+
+/// Ask before deleting `count` directories
+pub fn confirm_deletion(count: usize) -> bool {
+    let confirm = Confirm::with_theme(&ColorfulTheme::default())
+        .with_prompt(format!(
+            "Are you sure you want to delete {} directories?",
+            count
+        ))
+        .default(false)
+        .interact();
+
+    if matches!(confirm, Ok(true)) {
+        true
+    } else {
+        println!("❌ Deletion cancelled");
+        false
+    }
+}
+
+/// Remove directories the chosen way, `jobs` at a time (0 for one per CPU),
+/// reporting progress in bytes and failures per directory
+pub fn remove_directories(targets: &[Target], removal: Removal, jobs: usize) {
+    let total_size: u64 = targets.iter().map(|target| target.size).sum();
+
+    let remover = match Remover::start(removal) {
+        Ok(remover) => remover,
+        Err(e) => {
+            println!("❌ Failed to start a quarantine run: {}", e);
+            return;
+        }
+    };
+    let pool = match rayon::ThreadPoolBuilder::new().num_threads(jobs).build() {
+        Ok(pool) => pool,
+        Err(e) => {
+            println!("❌ Failed to start deletion workers: {}", e);
+            return;
+        }
+    };
+
     // Delete selected directories
-    println!("\n🗑️  Deleting directories...\n");
+    match removal {
+        Removal::Delete { .. } => println!("\n🗑️  Deleting directories...\n"),
+        Removal::Trash => println!("\n🗑️  Moving directories to the trash...\n"),
+        Removal::Quarantine => println!("\n📦 Moving directories to the quarantine...\n"),
+    }
 
-    let pb = ProgressBar::new(selections.len() as u64);
-    let style_result = ProgressStyle::default_bar()
-        .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} {msg}");
+    let pb = ProgressBar::new(total_size);
+    let style_result = ProgressStyle::default_bar().template(
+        "{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {decimal_bytes}/{decimal_total_bytes} ({decimal_bytes_per_sec}, ETA {eta}) {msg}",
+    );
 
     if let Ok(style) = style_result {
         pb.set_style(style.progress_chars("#>-"));
     }
 
-    let mut success_count = 0;
-    let mut error_count = 0;
+    let done = AtomicUsize::new(0);
+    let results: Vec<(u64, bool)> = pool.install(|| {
+        targets
+            .par_iter()
+            .map(|target| {
+                let started = Instant::now();
+                // The bar moves file by file, but never past the directory's
+                // size: hardlinked files may be counted more than once
+                let counted = AtomicU64::new(0);
+                let removed = |bytes: u64| {
+                    let before = counted.fetch_add(bytes, Ordering::Relaxed);
+                    pb.inc(bytes.min(target.size.saturating_sub(before)));
+                };
+
+                let result = remover.remove(target, &removed);
+                let counted = counted.load(Ordering::Relaxed).min(target.size);
+                // Whatever happened, the directory is processed
+                pb.inc(target.size - counted);
+                pb.set_message(format!(
+                    "{}/{} directories",
+                    done.fetch_add(1, Ordering::Relaxed) + 1,
+                    targets.len()
+                ));
+
+                match result {
+                    Ok(()) => {
+                        let elapsed = started.elapsed();
+                        report(
+                            &pb,
+                            format!(
+                                "  ✓ {} - {} in {:.1}s ({}/s)",
+                                target.path.display(),
+                                utils::format_size(target.size),
+                                elapsed.as_secs_f64(),
+                                utils::format_size(
+                                    (target.size as f64 / elapsed.as_secs_f64().max(0.001)) as u64
+                                )
+                            ),
+                        );
+                        (target.size, true)
+                    }
+                    Err(e) => {
+                        let mut message =
+                            format!("❌ Failed to remove {}: {}", target.path.display(), e);
+                        let blockers = deletion::blockers(&e);
+                        if blockers.len() > 1 {
+                            for blocker in blockers {
+                                message.push_str(&format!(
+                                    "\n     {}: {}",
+                                    blocker.path.display(),
+                                    blocker.error
+                                ));
+                            }
+                        }
+                        report(&pb, message);
+                        (0, false)
+                    }
+                }
+            })
+            .collect()
+    });
 
-    for &idx in &selections {
-        let item = &items[idx];
-        pb.set_message(format!("Deleting {}", item.path.display()));
+    pb.finish_with_message("Done!");
 
-        match delete_directory(&item.path) {
-            Ok(_) => {
-                success_count += 1;
+    let success_count = results.iter().filter(|(_, ok)| *ok).count();
+    let error_count = results.len() - success_count;
+    let freed: u64 = results.iter().map(|(bytes, _)| bytes).sum();
+    let total = utils::format_size(freed).bold().green();
+    match remover {
+        Remover::Delete { .. } => {
+            println!("\n✅ Successfully deleted {} directories", success_count);
+            if error_count > 0 {
+                println!("⚠️  Failed to delete {} directories", error_count);
             }
-            Err(e) => {
-                error_count += 1;
-                pb.println(format!(
-                    "❌ Failed to delete {}: {}",
-                    item.path.display(),
-                    e
-                ));
+            println!("💾 Reclaimed approximately {}", total);
+        }
+        Remover::Trash => {
+            println!("\n✅ Moved {} directories to the trash", success_count);
+            if error_count > 0 {
+                println!("⚠️  Failed to move {} directories", error_count);
+            }
+            println!("💾 Emptying the trash will reclaim approximately {}", total);
+        }
+        Remover::Quarantine(run) => {
+            let run = run.into_inner().unwrap_or_else(|e| e.into_inner());
+            let id = run.id.clone();
+            println!("\n✅ Quarantined {} directories", success_count);
+            if error_count > 0 {
+                println!("⚠️  Failed to move {} directories", error_count);
+            }
+            if let Err(e) = run.finish() {
+                println!("⚠️  Failed to update the journal of run {}: {}", id, e);
+            }
+            if success_count > 0 {
+                println!(
+                    "💾 Purging the quarantine will reclaim approximately {}",
+                    total
+                );
+                println!("↩️  Restore them with `sweepkit undo {}`", id);
             }
         }
-
-        pb.inc(1);
     }
+}
 
-    pb.finish_with_message("Done!");
-
-    println!("\n✅ Successfully deleted {} directories", success_count);
-    if error_count > 0 {
-        println!("⚠️  Failed to delete {} directories", error_count);
+/// Print a line above the progress bar, or plainly when the bar is hidden
+/// (output is not a terminal)
+fn report(pb: &ProgressBar, line: String) {
+    if pb.is_hidden() {
+        println!("{}", line);
+    } else {
+        pb.println(line);
     }
-    println!(
-        "💾 Reclaimed approximately {}",
-        utils::format_size(total_size).bold().green()
-    );
 }
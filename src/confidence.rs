@@ -0,0 +1,181 @@
+use crate::git::{self, GitStatus};
+use crate::languages::cachedir::CACHEDIR_TAG_SIGNATURE;
+use crate::matcher;
+use crate::scanner::FoundItem;
+use rayon::prelude::*;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Extensions of files that are only ever produced by tools
+const GENERATED_EXTENSIONS: &[&str] = &[
+    "o",
+    "obj",
+    "a",
+    "so",
+    "dylib",
+    "dll",
+    "lib",
+    "exe",
+    "pdb",
+    "ilk",
+    "exp",
+    "class",
+    "jar",
+    "war",
+    "pyc",
+    "pyo",
+    "whl",
+    "d",
+    "rlib",
+    "rmeta",
+    "gch",
+    "pch",
+    "map",
+    "tsbuildinfo",
+    "lst",
+];
+
+/// How sure the scanner is that a directory is a disposable artifact
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Confidence {
+    Low,
+    Medium,
+    High,
+}
+
+impl fmt::Display for Confidence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let confidence = match self {
+            Confidence::Low => "low",
+            Confidence::Medium => "medium",
+            Confidence::High => "high",
+        };
+        f.write_str(confidence)
+    }
+}
+
+/// Something found out about a directory beyond the pattern it matched
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Evidence {
+    /// Tagged with a valid `CACHEDIR.TAG`
+    CacheDirTag,
+    /// Only holds object files, bytecode and the like
+    GeneratedOnly,
+}
+
+impl fmt::Display for Evidence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let evidence = match self {
+            Evidence::CacheDirTag => "has CACHEDIR.TAG",
+            Evidence::GeneratedOnly => "only generated files",
+        };
+        f.write_str(evidence)
+    }
+}
+
+/// Score a directory from its pattern, git status and the evidence found
+/// about it
+///
+/// Committed content, or a repository git couldn't be asked about, always
+/// makes an item low confidence. Otherwise an item from an unambiguous
+/// pattern (`node_modules`) is high confidence, and one from an ambiguous
+/// pattern (`dist`, `build`) needs evidence to be trusted.
+fn score(ambiguous: bool, git: GitStatus, evidence: &[Evidence]) -> Confidence {
+    if matches!(git, GitStatus::Tracked(_) | GitStatus::Unknown) {
+        Confidence::Low
+    } else if !ambiguous || git == GitStatus::Ignored || evidence.contains(&Evidence::CacheDirTag) {
+        Confidence::High
+    } else if git == GitStatus::Untracked || evidence.contains(&Evidence::GeneratedOnly) {
+        Confidence::Medium
+    } else {
+        Confidence::Low
+    }
+}
+
+/// Look up the git status of every item, gather evidence and set its
+/// confidence
+pub fn assess(items: &mut [FoundItem]) {
+    let paths: Vec<PathBuf> = items.iter().map(|item| item.path.clone()).collect();
+    let statuses = git::statuses(&paths);
+
+    items.par_iter_mut().zip(statuses).for_each(|(item, git)| {
+        let mut evidence = Vec::new();
+
+        if matcher::contains_file(&item.path, "CACHEDIR.TAG", Some(CACHEDIR_TAG_SIGNATURE)) {
+            evidence.push(Evidence::CacheDirTag);
+        }
+
+        // Only worth a walk when nothing stronger settled it
+        if score(item.ambiguous, git, &evidence) < Confidence::High
+            && only_generated_files(&item.path)
+        {
+            evidence.push(Evidence::GeneratedOnly);
+        }
+
+        item.git = git;
+        item.confidence = score(item.ambiguous, git, &evidence);
+        item.evidence = evidence;
+    });
+}
+
+/// Whether `dir` holds files, all with a generated-only extension
+fn only_generated_files(dir: &Path) -> bool {
+    count_generated(dir).is_some_and(|count| count > 0)
+}
+
+/// Number of files below `dir`, or None as soon as one isn't generated
+fn count_generated(dir: &Path) -> Option<usize> {
+    let mut count = 0;
+
+    for entry in fs::read_dir(dir).ok()? {
+        let entry = entry.ok()?;
+        let path = entry.path();
+
+        if entry.file_type().ok()?.is_dir() {
+            count += count_generated(&path)?;
+        } else {
+            let ext = path.extension().and_then(|ext| ext.to_str())?;
+            if !GENERATED_EXTENSIONS.contains(&ext) {
+                return None;
+            }
+            count += 1;
+        }
+    }
+
+    Some(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn committed_or_unknown_content_is_low_confidence() {
+        for git in [GitStatus::Tracked(3), GitStatus::Unknown] {
+            assert_eq!(score(false, git, &[]), Confidence::Low);
+            assert_eq!(score(true, git, &[Evidence::CacheDirTag]), Confidence::Low);
+        }
+    }
+
+    #[test]
+    fn unambiguous_patterns_are_high_confidence() {
+        assert_eq!(score(false, GitStatus::NotInRepo, &[]), Confidence::High);
+        assert_eq!(score(false, GitStatus::Untracked, &[]), Confidence::High);
+    }
+
+    #[test]
+    fn ambiguous_patterns_need_evidence() {
+        assert_eq!(score(true, GitStatus::NotInRepo, &[]), Confidence::Low);
+        assert_eq!(score(true, GitStatus::Untracked, &[]), Confidence::Medium);
+        assert_eq!(
+            score(true, GitStatus::NotInRepo, &[Evidence::GeneratedOnly]),
+            Confidence::Medium
+        );
+        assert_eq!(score(true, GitStatus::Ignored, &[]), Confidence::High);
+        assert_eq!(
+            score(true, GitStatus::NotInRepo, &[Evidence::CacheDirTag]),
+            Confidence::High
+        );
+    }
+}
@@ -0,0 +1,81 @@
+use crate::filter;
+use crate::quarantine;
+use serde::Deserialize;
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// User configuration, read from `~/.config/sweepkit/config.toml` on Linux
+///
+/// ```toml
+/// # Scanned by `sweepkit scan` / `sweepkit clean` when no --path is given
+/// roots = ["~/work", "~/oss", "/data/builds"]
+///
+/// # Move cleaned folders to the trash instead of deleting them
+/// trash = true
+///
+/// # Purge folders quarantined by `clean --quarantine` after this long
+/// quarantine_expiry = "14d"
+/// ```
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Workspace roots to scan by default
+    pub roots: Vec<String>,
+    /// Make `sweepkit clean` use the trash unless `--no-trash` is passed
+    pub trash: bool,
+    /// How long quarantined folders are kept (e.g., "7d")
+    pub quarantine_expiry: Option<String>,
+}
+
+impl Config {
+    /// Location of the config file
+    pub fn path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("sweepkit").join("config.toml"))
+    }
+
+    /// Load the config, falling back to defaults if it is missing or invalid
+    pub fn load() -> Config {
+        let Some(path) = Config::path() else {
+            return Config::default();
+        };
+        let Ok(contents) = fs::read_to_string(&path) else {
+            return Config::default();
+        };
+
+        match toml::from_str(&contents) {
+            Ok(config) => config,
+            Err(e) => {
+                eprintln!("⚠️  Ignoring invalid config {}: {}", path.display(), e);
+                Config::default()
+            }
+        }
+    }
+
+    /// How long quarantined folders are kept before `clean --quarantine`
+    /// purges them
+    pub fn quarantine_expiry(&self) -> Duration {
+        let Some(expiry) = &self.quarantine_expiry else {
+            return quarantine::DEFAULT_EXPIRY;
+        };
+        filter::parse_duration(expiry).unwrap_or_else(|e| {
+            eprintln!("⚠️  Ignoring invalid quarantine_expiry: {}", e);
+            quarantine::DEFAULT_EXPIRY
+        })
+    }
+
+    /// Configured roots, with `~` expanded to the home directory
+    pub fn roots(&self) -> Vec<PathBuf> {
+        self.roots.iter().map(|root| expand_home(root)).collect()
+    }
+}
+
+/// Expand a leading `~` to the home directory
+fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix('~'), dirs::home_dir()) {
+        (Some(rest), Some(home)) if rest.is_empty() || rest.starts_with('/') => {
+            home.join(rest.trim_start_matches('/'))
+        }
+        _ => PathBuf::from(path),
+    }
+}
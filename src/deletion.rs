@@ -0,0 +1,310 @@
+use crate::sizing;
+use crate::utils;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// A file or directory that kept a folder from being deleted, and why
+#[derive(Debug)]
+pub struct Blocker {
+    pub path: PathBuf,
+    pub error: io::Error,
+}
+
+/// Everything that kept a folder from being deleted, carried inside the
+/// `io::Error` returned by `delete_directory`
+#[derive(Debug)]
+pub struct Blocked(pub Vec<Blocker>);
+
+impl fmt::Display for Blocked {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0.as_slice() {
+            [blocker] => write!(f, "{}: {}", blocker.path.display(), blocker.error),
+            blockers => write!(f, "{} entries couldn't be removed", blockers.len()),
+        }
+    }
+}
+
+impl Error for Blocked {}
+
+/// The entries that blocked a deletion, if `error` came from
+/// `delete_directory`
+pub fn blockers(error: &io::Error) -> &[Blocker] {
+    error
+        .get_ref()
+        .and_then(|inner| inner.downcast_ref::<Blocked>())
+        .map(|blocked| blocked.0.as_slice())
+        .unwrap_or(&[])
+}
+
+/// Delete a directory with all its contents, calling `removed` with the
+/// allocated bytes of each file as it goes
+///
+/// A symlinked artifact only loses its link; the tree it points to is kept.
+/// Deletion goes on past entries that can't be removed, and the error lists
+/// them (see `blockers`). With `fix_permissions`, directories of the tree
+/// that the user owns but can't read or write get `u+rwx` and the removal is
+/// retried; directories owned by someone else are never touched.
+pub fn delete_directory(
+    path: &Path,
+    fix_permissions: bool,
+    removed: &dyn Fn(u64),
+) -> Result<(), io::Error> {
+    if fs::symlink_metadata(path)?.file_type().is_symlink() {
+        return remove_link(path);
+    }
+
+    let mut deletion = Deletion {
+        fix_permissions,
+        removed,
+        blocked: Vec::new(),
+    };
+    deletion.delete_tree(path);
+    if deletion.blocked.is_empty() {
+        // The folder's own parent isn't part of the tree: never fixed
+        if let Err(error) = fs::remove_dir(path) {
+            deletion.blocked.push(Blocker {
+                path: path.to_path_buf(),
+                error,
+            });
+        }
+    }
+
+    match deletion.blocked.first() {
+        None => Ok(()),
+        Some(first) => Err(io::Error::new(
+            first.error.kind(),
+            Blocked(deletion.blocked),
+        )),
+    }
+}
+
+/// Remove a symlink itself
+fn remove_link(path: &Path) -> Result<(), io::Error> {
+    // Directory symlinks are files on Unix but directories on Windows
+    fs::remove_file(path).or_else(|_| fs::remove_dir(path))
+}
+
+struct Deletion<'a> {
+    fix_permissions: bool,
+    removed: &'a dyn Fn(u64),
+    blocked: Vec<Blocker>,
+}
+
+impl Deletion<'_> {
+    /// Empty `dir`, leaving it and whatever couldn't be removed in place
+    fn delete_tree(&mut self, dir: &Path) {
+        let entries = match self.attempt(dir, || fs::read_dir(dir)) {
+            Ok(entries) => entries,
+            Err(error) => return self.block(dir, dir, error),
+        };
+
+        for entry in entries {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(error) => return self.block(dir, dir, error),
+            };
+            let path = entry.path();
+            // Never follows symlinks: they are removed like files
+            let file_type = match entry.file_type() {
+                Ok(file_type) => file_type,
+                Err(error) => {
+                    self.block(dir, &path, error);
+                    continue;
+                }
+            };
+
+            let result = if file_type.is_dir() {
+                let blocked = self.blocked.len();
+                self.delete_tree(&path);
+                if self.blocked.len() > blocked {
+                    // Already reported: it can't be empty
+                    continue;
+                }
+                self.attempt(dir, || fs::remove_dir(&path))
+            } else if file_type.is_symlink() {
+                self.attempt(dir, || remove_link(&path))
+            } else {
+                let bytes = entry
+                    .metadata()
+                    .map(|metadata| sizing::allocated_len(&metadata))
+                    .unwrap_or(0);
+                self.attempt(dir, || fs::remove_file(&path))
+                    .map(|()| (self.removed)(bytes))
+            };
+
+            if let Err(error) = result {
+                if error.kind() == io::ErrorKind::PermissionDenied && !accessible(dir) {
+                    // Nothing else in the directory can go: report it once
+                    return self.block(dir, dir, error);
+                }
+                self.block(dir, &path, error);
+            }
+        }
+    }
+
+    /// Run `op` on an entry of `dir`, retrying once after making `dir`
+    /// accessible if permissions are to be fixed
+    fn attempt<T>(&self, dir: &Path, op: impl Fn() -> io::Result<T>) -> io::Result<T> {
+        match op() {
+            Err(e) if e.kind() == io::ErrorKind::PermissionDenied && self.fix_permissions => {
+                if make_accessible(dir) {
+                    op()
+                } else {
+                    Err(e)
+                }
+            }
+            result => result,
+        }
+    }
+
+    /// Record `path` as blocking the deletion, explaining permission errors
+    /// on `dir`
+    fn block(&mut self, dir: &Path, path: &Path, error: io::Error) {
+        let error = if error.kind() == io::ErrorKind::PermissionDenied && !accessible(dir) {
+            let reason = match owned_by_user(dir) {
+                false => "directory owned by another user, left as is",
+                true if self.fix_permissions => "directory still not writable",
+                true => "read-only or unreadable directory (pass --fix-permissions to fix it)",
+            };
+            io::Error::new(error.kind(), reason)
+        } else {
+            error
+        };
+        self.blocked.push(Blocker {
+            path: path.to_path_buf(),
+            error,
+        });
+    }
+}
+
+/// Whether the user may list `dir` and remove entries from it
+#[cfg(unix)]
+fn accessible(dir: &Path) -> bool {
+    use std::os::unix::ffi::OsStrExt;
+
+    let Ok(path) = std::ffi::CString::new(dir.as_os_str().as_bytes()) else {
+        return false;
+    };
+    unsafe { libc::access(path.as_ptr(), libc::R_OK | libc::W_OK | libc::X_OK) == 0 }
+}
+
+#[cfg(not(unix))]
+fn accessible(_dir: &Path) -> bool {
+    true
+}
+
+#[cfg(unix)]
+fn owned_by_user(dir: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+
+    fs::symlink_metadata(dir).is_ok_and(|metadata| metadata.uid() == utils::uid())
+}
+
+#[cfg(not(unix))]
+fn owned_by_user(_dir: &Path) -> bool {
+    true
+}
+
+/// Give the user read, write and search permission on a directory they own
+/// and lack some of them on; returns whether anything changed
+#[cfg(unix)]
+fn make_accessible(dir: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+
+    let Ok(metadata) = fs::symlink_metadata(dir) else {
+        return false;
+    };
+    let mode = metadata.permissions().mode();
+    if !metadata.is_dir() || !owned_by_user(dir) || mode & 0o700 == 0o700 {
+        return false;
+    }
+    fs::set_permissions(dir, fs::Permissions::from_mode(mode | 0o700)).is_ok()
+}
+
+#[cfg(not(unix))]
+fn make_accessible(_dir: &Path) -> bool {
+    false
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::testing::test_dir;
+    use std::os::unix::fs::PermissionsExt;
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    #[test]
+    fn trees_are_deleted_reporting_freed_bytes() {
+        let root = test_dir("delete-tree");
+        let tree = root.join("node_modules");
+        fs::create_dir_all(tree.join("pkg/lib")).unwrap();
+        fs::write(tree.join("pkg/index.js"), vec![b'a'; 5_000]).unwrap();
+        fs::write(tree.join("pkg/lib/deep.js"), vec![b'b'; 70_000]).unwrap();
+        std::os::unix::fs::symlink("index.js", tree.join("pkg/main.js")).unwrap();
+        let expected: u64 = ["pkg/index.js", "pkg/lib/deep.js"]
+            .iter()
+            .map(|file| sizing::allocated_len(&fs::metadata(tree.join(file)).unwrap()))
+            .sum();
+
+        let removed = AtomicU64::new(0);
+        let count = |bytes| {
+            removed.fetch_add(bytes, Ordering::Relaxed);
+        };
+        delete_directory(&tree, false, &count).unwrap();
+        assert!(!tree.exists());
+        assert_eq!(removed.into_inner(), expected);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn symlinked_folders_only_lose_the_link() {
+        let root = test_dir("delete-link");
+        fs::create_dir_all(root.join("store/pkg")).unwrap();
+        fs::write(root.join("store/pkg/index.js"), "").unwrap();
+        std::os::unix::fs::symlink(root.join("store"), root.join("node_modules")).unwrap();
+
+        delete_directory(&root.join("node_modules"), false, &|_| {}).unwrap();
+        assert!(fs::symlink_metadata(root.join("node_modules")).is_err());
+        assert!(root.join("store/pkg/index.js").is_file());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn read_only_directories_block_until_fixed() {
+        if utils::uid() == 0 {
+            // Permissions never stop root
+            eprintln!("running as root, skipping");
+            return;
+        }
+        let root = test_dir("delete-blocked");
+        let tree = root.join("target");
+        let locked = tree.join("locked");
+        fs::create_dir_all(&locked).unwrap();
+        fs::write(locked.join("file"), "").unwrap();
+        fs::write(tree.join("other"), "").unwrap();
+        fs::set_permissions(&locked, fs::Permissions::from_mode(0o500)).unwrap();
+
+        let error = delete_directory(&tree, false, &|_| {}).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::PermissionDenied);
+        let blockers = blockers(&error);
+        assert_eq!(blockers.len(), 1);
+        assert_eq!(blockers[0].path, locked);
+        assert_eq!(
+            blockers[0].error.to_string(),
+            "read-only or unreadable directory (pass --fix-permissions to fix it)"
+        );
+        // Everything else went
+        assert!(locked.join("file").exists());
+        assert!(!tree.join("other").exists());
+
+        delete_directory(&tree, true, &|_| {}).unwrap();
+        assert!(!tree.exists());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,146 @@
+use globset::{Glob, GlobBuilder, GlobSet, GlobSetBuilder};
+use std::path::{Component, Path, PathBuf};
+use std::time::Duration;
+
+/// Parse an age such as "30d", "12h" or "2w" (used as a clap value parser)
+pub fn parse_duration(value: &str) -> Result<Duration, String> {
+    let value = value.trim();
+    let split = value
+        .find(|c: char| !c.is_ascii_digit())
+        .ok_or_else(|| format!("missing unit in {:?} (use s, m, h, d, w or y)", value))?;
+    let (number, unit) = value.split_at(split);
+    let number: u64 = number
+        .parse()
+        .map_err(|_| format!("invalid number in {:?}", value))?;
+
+    let seconds = match unit.trim() {
+        "s" => 1,
+        "m" | "min" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        "y" => 365 * 24 * 60 * 60,
+        other => return Err(format!("unknown unit {:?} (use s, m, h, d, w or y)", other)),
+    };
+
+    number
+        .checked_mul(seconds)
+        .map(Duration::from_secs)
+        .ok_or_else(|| format!("{:?} is too long", value))
+}
+
+/// Check that an `--exclude` pattern is a valid glob (used as a clap value parser)
+pub fn parse_exclude(pattern: &str) -> Result<String, String> {
+    Glob::new(&expand_pattern(pattern))
+        .map(|_| pattern.to_string())
+        .map_err(|e| e.to_string())
+}
+
+/// Expand `~/` and turn relative path patterns into "anywhere below" patterns
+fn expand_pattern(pattern: &str) -> String {
+    let pattern = pattern.trim_end_matches('/');
+
+    if let Some(rest) = pattern.strip_prefix("~/") {
+        if let Some(home) = dirs::home_dir() {
+            return home.join(rest).to_string_lossy().into_owned();
+        }
+    }
+
+    if pattern.starts_with('/') || pattern.starts_with("**") {
+        pattern.to_string()
+    } else {
+        format!("**/{}", pattern)
+    }
+}
+
+/// Directories to leave out of a scan entirely
+///
+/// Patterns without a `/` (e.g. `vendor`, `*.bak`) are matched against the
+/// directory name. Patterns with one (e.g. `~/.local/share`, `/mnt/*`,
+/// `docs/build`) are matched against the full path, so whole subtrees can be
+/// skipped.
+#[derive(Debug, Clone, Default)]
+pub struct ExcludeSet {
+    names: Option<GlobSet>,
+    paths: Option<GlobSet>,
+    /// Base for relative scan paths, so path patterns always see absolute paths
+    cwd: PathBuf,
+}
+
+impl ExcludeSet {
+    pub fn new(patterns: &[String]) -> ExcludeSet {
+        let mut names = GlobSetBuilder::new();
+        let mut paths = GlobSetBuilder::new();
+        let mut has_names = false;
+        let mut has_paths = false;
+
+        for pattern in patterns {
+            if pattern.trim_end_matches('/').contains('/') {
+                let glob = GlobBuilder::new(&expand_pattern(pattern))
+                    .literal_separator(true)
+                    .build();
+                if let Ok(glob) = glob {
+                    paths.add(glob);
+                    has_paths = true;
+                }
+            } else if let Ok(glob) = Glob::new(pattern) {
+                names.add(glob);
+                has_names = true;
+            }
+        }
+
+        ExcludeSet {
+            names: names.build().ok().filter(|_| has_names),
+            paths: paths.build().ok().filter(|_| has_paths),
+            cwd: std::env::current_dir().unwrap_or_default(),
+        }
+    }
+
+    /// Whether a directory (and everything below it) should be skipped
+    pub fn is_excluded(&self, path: &Path) -> bool {
+        if let Some(names) = &self.names {
+            if path.file_name().is_some_and(|name| names.is_match(name)) {
+                return true;
+            }
+        }
+
+        if let Some(paths) = &self.paths {
+            let absolute: PathBuf = self
+                .cwd
+                .join(path)
+                .components()
+                .filter(|c| !matches!(c, Component::CurDir))
+                .collect();
+            return paths.is_match(absolute);
+        }
+
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn durations_take_a_unit() {
+        assert_eq!(parse_duration("90s"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_duration("5min"), Ok(Duration::from_secs(300)));
+        assert_eq!(parse_duration(" 2 d "), Ok(Duration::from_secs(2 * 86_400)));
+        assert_eq!(parse_duration("1y"), Ok(Duration::from_secs(365 * 86_400)));
+        assert!(parse_duration("30").is_err());
+        assert!(parse_duration("3 months").is_err());
+        assert!(parse_duration("d").is_err());
+        assert!(parse_duration("-1d").is_err());
+    }
+
+    #[test]
+    fn overlong_durations_are_rejected() {
+        assert!(parse_duration("999999999999y").is_err());
+        assert!(parse_duration("99999999999999999999s").is_err());
+        assert_eq!(
+            parse_duration("18446744073709551615s"),
+            Ok(Duration::from_secs(u64::MAX))
+        );
+    }
+}
@@ -0,0 +1,277 @@
+use crate::utils;
+use rayon::prelude::*;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+use std::thread;
+
+/// Paths handed to one `git ls-files` call, to stay clear of argv limits
+const PATHS_PER_CALL: usize = 256;
+
+/// Where a directory stands with git
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum GitStatus {
+    /// Not inside a git work tree
+    #[default]
+    NotInRepo,
+    /// Inside a git work tree, but git couldn't be asked (not installed,
+    /// repository owned by another user, ...)
+    Unknown,
+    /// Matched by `.gitignore`, `.git/info/exclude` or the global excludes
+    Ignored,
+    /// Neither ignored nor holding anything from the index
+    Untracked,
+    /// Has this many files in the index below it
+    Tracked(usize),
+}
+
+impl fmt::Display for GitStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GitStatus::NotInRepo => f.write_str("not in a git repository"),
+            GitStatus::Unknown => f.write_str("git status unknown"),
+            GitStatus::Ignored => f.write_str("gitignored"),
+            GitStatus::Untracked => f.write_str("untracked"),
+            GitStatus::Tracked(1) => f.write_str("1 tracked file"),
+            GitStatus::Tracked(count) => write!(f, "{} tracked files", count),
+        }
+    }
+}
+
+/// Git status of each of `dirs`, in order
+///
+/// Git is asked once per repository for all of its directories. When it
+/// can't answer, a warning says why and the repository's directories are
+/// `Unknown`.
+pub fn statuses(dirs: &[PathBuf]) -> Vec<GitStatus> {
+    let locations: Vec<Option<(PathBuf, PathBuf)>> = dirs.iter().map(|dir| locate(dir)).collect();
+
+    let mut by_repo: HashMap<&Path, Vec<PathBuf>> = HashMap::new();
+    for (repo, relative) in locations.iter().flatten() {
+        by_repo.entry(repo).or_default().push(relative.clone());
+    }
+    let states: HashMap<&Path, RepoState> = by_repo
+        .into_par_iter()
+        .filter_map(|(repo, paths)| match RepoState::read(repo, &paths) {
+            Ok(state) => Some((repo, state)),
+            Err(e) => {
+                eprintln!("⚠️  Can't ask git about {}: {}", repo.display(), e);
+                None
+            }
+        })
+        .collect();
+
+    locations
+        .iter()
+        .map(|location| {
+            let Some((repo, relative)) = location else {
+                return GitStatus::NotInRepo;
+            };
+            let Some(state) = states.get(repo.as_path()) else {
+                return GitStatus::Unknown;
+            };
+            // A file force-added inside an ignored directory is still tracked
+            match state.tracked_below(relative) {
+                0 if state.ignored.contains(relative) => GitStatus::Ignored,
+                0 => GitStatus::Untracked,
+                count => GitStatus::Tracked(count),
+            }
+        })
+        .collect()
+}
+
+/// Repository of a directory, and the directory's path relative to it
+pub fn locate(path: &Path) -> Option<(PathBuf, PathBuf)> {
+    let canonical = utils::absolute_keep_link(path).ok()?;
+    let repo = find_repo(canonical.parent()?)?;
+    let relative = canonical.strip_prefix(&repo).ok()?.to_path_buf();
+    Some((repo, relative))
+}
+
+/// What git knows about the directories of one repository
+struct RepoState {
+    ignored: HashSet<PathBuf>,
+    tracked: Vec<PathBuf>,
+}
+
+impl RepoState {
+    fn read(repo: &Path, paths: &[PathBuf]) -> io::Result<RepoState> {
+        Ok(RepoState {
+            ignored: ignored(repo, paths)?,
+            tracked: tracked_files(repo, paths)?,
+        })
+    }
+
+    fn tracked_below(&self, dir: &Path) -> usize {
+        self.tracked
+            .iter()
+            .filter(|file| file.starts_with(dir))
+            .count()
+    }
+}
+
+/// Root of the git work tree containing a (canonical) path, if any
+pub fn find_repo(path: &Path) -> Option<PathBuf> {
+    // `.git` is a directory in a normal checkout and a file in worktrees and
+    // submodules
+    path.ancestors()
+        .find(|dir| dir.join(".git").exists())
+        .map(Path::to_path_buf)
+}
+
+/// Which of `paths` (directories, relative to `repo`) git ignores, following
+/// `.gitignore` files, `.git/info/exclude` and the global excludes file
+///
+/// Fails if git can't be run or refuses the repository.
+pub fn ignored(repo: &Path, paths: &[PathBuf]) -> io::Result<HashSet<PathBuf>> {
+    let mut input = Vec::new();
+    for path in paths {
+        input.extend_from_slice(path_bytes(path)?);
+        input.extend_from_slice(b"/\0");
+    }
+
+    let mut child = Command::new("git")
+        .arg("-C")
+        .arg(repo)
+        .args(["check-ignore", "-z", "--stdin"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(not_found)?;
+
+    // Feed stdin from another thread: git answers path by path, and a full
+    // stdout pipe would otherwise block both sides
+    let mut stdin = child
+        .stdin
+        .take()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::BrokenPipe, "no stdin for git"))?;
+    let writer = thread::spawn(move || stdin.write_all(&input));
+
+    // Exit status 1 just means nothing is ignored
+    let output = child.wait_with_output()?;
+    if !matches!(output.status.code(), Some(0 | 1)) {
+        return Err(failure(&output));
+    }
+    writer
+        .join()
+        .map_err(|_| io::Error::new(io::ErrorKind::Other, "writing to git failed"))??;
+
+    split_nul(&output.stdout)
+        .map(|path| path.map(|path| path.components().collect()))
+        .collect()
+}
+
+/// Files tracked in the index below any of `paths` (relative to `repo`)
+///
+/// Fails if git can't be run or refuses the repository.
+pub fn tracked_files(repo: &Path, paths: &[PathBuf]) -> io::Result<Vec<PathBuf>> {
+    let mut tracked = Vec::new();
+
+    for chunk in paths.chunks(PATHS_PER_CALL) {
+        let output = Command::new("git")
+            .arg("-C")
+            .arg(repo)
+            .args(["ls-files", "-z", "--"])
+            .args(chunk)
+            .output()
+            .map_err(not_found)?;
+        if !output.status.success() {
+            return Err(failure(&output));
+        }
+        for file in split_nul(&output.stdout) {
+            tracked.push(file?);
+        }
+    }
+
+    Ok(tracked)
+}
+
+/// Explain a failure to start git
+fn not_found(e: io::Error) -> io::Error {
+    if e.kind() == io::ErrorKind::NotFound {
+        io::Error::new(e.kind(), "git is not installed or not on PATH")
+    } else {
+        e
+    }
+}
+
+/// Error for a failed git command, with the first line git printed
+fn failure(output: &Output) -> io::Error {
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let message = stderr
+        .lines()
+        .map(|line| line.trim_start_matches("fatal: "))
+        .find(|line| !line.trim().is_empty())
+        .map(str::to_string)
+        .unwrap_or_else(|| format!("git exited with {}", output.status));
+    io::Error::new(io::ErrorKind::Other, message)
+}
+
+/// Paths of NUL-separated git output
+///
+/// Paths are bytes to git: one that can't be represented here is an error,
+/// never skipped, so a tracked file can't go unnoticed.
+fn split_nul(output: &[u8]) -> impl Iterator<Item = io::Result<PathBuf>> + '_ {
+    output
+        .split(|&b| b == 0)
+        .filter(|record| !record.is_empty())
+        .map(bytes_path)
+}
+
+#[cfg(unix)]
+fn bytes_path(bytes: &[u8]) -> io::Result<PathBuf> {
+    use std::os::unix::ffi::OsStrExt;
+
+    Ok(PathBuf::from(std::ffi::OsStr::from_bytes(bytes)))
+}
+
+/// Git uses UTF-8 paths outside Unix
+#[cfg(not(unix))]
+fn bytes_path(bytes: &[u8]) -> io::Result<PathBuf> {
+    std::str::from_utf8(bytes).map(PathBuf::from).map_err(|_| {
+        let path = String::from_utf8_lossy(bytes);
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("git listed an unreadable path {path:?}"),
+        )
+    })
+}
+
+#[cfg(unix)]
+fn path_bytes(path: &Path) -> io::Result<&[u8]> {
+    use std::os::unix::ffi::OsStrExt;
+
+    Ok(path.as_os_str().as_bytes())
+}
+
+#[cfg(not(unix))]
+fn path_bytes(path: &Path) -> io::Result<&[u8]> {
+    path.to_str().map(str::as_bytes).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("can't pass {} to git", path.display()),
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn paths_that_are_not_utf8_are_kept() {
+        use std::os::unix::ffi::OsStrExt;
+
+        let paths: Vec<PathBuf> = split_nul(b"node_modules/\xff.js\0package.json\0")
+            .collect::<io::Result<_>>()
+            .unwrap();
+        assert_eq!(paths.len(), 2);
+        assert_eq!(paths[0].as_os_str().as_bytes(), b"node_modules/\xff.js");
+        assert!(paths[0].starts_with("node_modules"));
+        assert_eq!(paths[1], Path::new("package.json"));
+    }
+}
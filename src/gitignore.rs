@@ -0,0 +1,195 @@
+use crate::git::{self, GitStatus};
+use crate::scanner::FoundItem;
+use colored::Colorize;
+use std::collections::BTreeMap;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// Lines to append to one `.gitignore` file
+pub struct Change {
+    pub repo: PathBuf,
+    /// Path of the file, relative to `repo`
+    pub file: PathBuf,
+    /// Current content, None if the file doesn't exist yet
+    pub existing: Option<String>,
+    pub rules: Vec<String>,
+}
+
+/// Work out the `.gitignore` lines that would ignore every untracked item
+///
+/// Rules that only depend on the directory name (`__pycache__/`) go into the
+/// repository's top-level `.gitignore`, where one line covers every project.
+/// Others are anchored to the item (`/target/`) and go into the nearest
+/// `.gitignore` above it. Lines a file already has are never proposed twice.
+pub fn propose(items: &[FoundItem]) -> Vec<Change> {
+    let mut rules: BTreeMap<(PathBuf, PathBuf), Vec<String>> = BTreeMap::new();
+
+    for item in items.iter().filter(|item| item.git == GitStatus::Untracked) {
+        let Some((repo, relative)) = git::locate(&item.path) else {
+            continue;
+        };
+        let (file, rule) = match &item.ignore_rule {
+            Some(rule) => (PathBuf::from(".gitignore"), rule.clone()),
+            None => {
+                let dir = nearest_gitignore_dir(&repo, &relative);
+                let anchored = relative.strip_prefix(&dir).unwrap_or(&relative);
+                (dir.join(".gitignore"), anchor(anchored))
+            }
+        };
+        rules.entry((repo, file)).or_default().push(rule);
+    }
+
+    rules
+        .into_iter()
+        .filter_map(|((repo, file), mut rules)| {
+            let existing = fs::read_to_string(repo.join(&file)).ok();
+            let present: Vec<&str> = existing
+                .as_deref()
+                .unwrap_or_default()
+                .lines()
+                .map(normalize)
+                .collect();
+
+            rules.sort();
+            rules.dedup();
+            rules.retain(|rule| !present.contains(&normalize(rule)));
+
+            (!rules.is_empty()).then_some(Change {
+                repo,
+                file,
+                existing,
+                rules,
+            })
+        })
+        .collect()
+}
+
+/// Directory (relative to the repository) of the closest `.gitignore` above
+/// an item, or the repository root if there is none
+fn nearest_gitignore_dir(repo: &Path, relative: &Path) -> PathBuf {
+    relative
+        .ancestors()
+        .skip(1)
+        .find(|dir| repo.join(dir).join(".gitignore").is_file())
+        .unwrap_or(Path::new(""))
+        .to_path_buf()
+}
+
+/// `.gitignore` line for exactly one directory, given relative to the
+/// directory of the `.gitignore` file
+fn anchor(relative: &Path) -> String {
+    let parts: Vec<String> = relative
+        .components()
+        .map(|part| part.as_os_str().to_string_lossy().into_owned())
+        .collect();
+    format!("/{}/", parts.join("/"))
+}
+
+/// A rule as git sees it: surrounding blanks and a trailing `/` don't matter
+/// for a directory
+fn normalize(line: &str) -> &str {
+    line.trim().trim_end_matches('/')
+}
+
+/// Print the proposed changes as a unified diff
+pub fn display_diff(changes: &[Change]) {
+    for (idx, change) in changes.iter().enumerate() {
+        if idx == 0 || changes[idx - 1].repo != change.repo {
+            println!(
+                "{} {}",
+                "📁".bold(),
+                change.repo.display().to_string().bold()
+            );
+        }
+
+        let (old, start) = match &change.existing {
+            Some(content) => (
+                format!("a/{}", change.file.display()),
+                content.lines().count(),
+            ),
+            None => ("/dev/null".to_string(), 0),
+        };
+
+        println!("{}", format!("--- {}", old).bold());
+        println!("{}", format!("+++ b/{}", change.file.display()).bold());
+        println!(
+            "{}",
+            format!("@@ -{},0 +{},{} @@", start, start + 1, change.rules.len()).cyan()
+        );
+        for rule in &change.rules {
+            println!("{}", format!("+{}", rule).green());
+        }
+    }
+}
+
+/// Append the proposed lines, creating `.gitignore` files as needed
+pub fn apply(change: &Change) -> io::Result<()> {
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(change.repo.join(&change.file))?;
+
+    let mut text = String::new();
+    if change
+        .existing
+        .as_deref()
+        .is_some_and(|content| !content.is_empty() && !content.ends_with('\n'))
+    {
+        text.push('\n');
+    }
+    for rule in &change.rules {
+        text.push_str(rule);
+        text.push('\n');
+    }
+    file.write_all(text.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::languages::DetectionPattern;
+
+    #[test]
+    fn anchored_rules_match_one_directory() {
+        assert_eq!(anchor(Path::new("target")), "/target/");
+        assert_eq!(anchor(Path::new("web/app/dist")), "/web/app/dist/");
+    }
+
+    #[test]
+    fn existing_lines_are_compared_as_git_sees_them() {
+        assert_eq!(normalize("/target/"), "/target");
+        assert_eq!(normalize("  /target  "), "/target");
+        assert_eq!(normalize("__pycache__/"), normalize("__pycache__"));
+        // Anchoring changes what a rule matches
+        assert_ne!(normalize("/target/"), normalize("target/"));
+    }
+
+    #[test]
+    fn name_only_patterns_have_a_rule() {
+        let rule = |pattern: DetectionPattern| pattern.gitignore_rule();
+        assert_eq!(
+            rule(DetectionPattern::DirectoryName("__pycache__".to_string())),
+            Some("__pycache__/".to_string())
+        );
+        assert_eq!(
+            rule(DetectionPattern::GlobPattern("*.egg-info".to_string())),
+            Some("*.egg-info/".to_string())
+        );
+        assert_eq!(
+            rule(DetectionPattern::GlobPattern("{dist,out}".to_string())),
+            None
+        );
+        assert_eq!(
+            rule(DetectionPattern::GlobPattern("packages/*/dist".to_string())),
+            None
+        );
+        assert_eq!(
+            rule(DetectionPattern::DirectoryWithSibling {
+                dir_name: "target".to_string(),
+                sibling: "Cargo.toml".to_string(),
+            }),
+            None
+        );
+    }
+}
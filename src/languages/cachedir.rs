@@ -0,0 +1,39 @@
+use super::{ArtifactKind, ArtifactPattern, DetectionPattern, GlobalCachePath, LanguageCleaner};
+
+/// First line of a valid `CACHEDIR.TAG` (https://bford.info/cachedir/)
+pub const CACHEDIR_TAG_SIGNATURE: &str = "Signature: 8a477f597d28d172789f06886806bc55";
+
+/// Tool caches of any ecosystem that tag themselves with `CACHEDIR.TAG`
+///
+/// Registered last, so directories also known to a specific ecosystem (Cargo's
+/// `target`, `.pytest_cache`, ...) are attributed to it instead.
+pub struct CacheDirCleaner;
+
+impl LanguageCleaner for CacheDirCleaner {
+    fn name(&self) -> &str {
+        "Cache"
+    }
+
+    fn icon(&self) -> &str {
+        "🗄️"
+    }
+
+    fn project_patterns(&self) -> Vec<ArtifactPattern> {
+        vec![ArtifactPattern::new(
+            "Cache",
+            ArtifactKind::Cache,
+            DetectionPattern::ContainsFile {
+                file: "CACHEDIR.TAG".to_string(),
+                signature: Some(CACHEDIR_TAG_SIGNATURE.to_string()),
+            },
+        )]
+    }
+
+    fn project_markers(&self) -> Vec<String> {
+        vec![]
+    }
+
+    fn global_cache_paths(&self) -> Vec<GlobalCachePath> {
+        vec![]
+    }
+}
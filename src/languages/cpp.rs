@@ -1,4 +1,7 @@
-use super::{DetectionPattern, GlobalCachePath, LanguageCleaner, OrphanedPackage};
+use super::{
+    ArtifactKind, ArtifactPattern, DetectionPattern, GlobalCachePath, LanguageCleaner,
+    OrphanedPackage,
+};
 
 pub struct CppCleaner;
 
@@ -11,21 +14,62 @@ impl LanguageCleaner for CppCleaner {
         "⚙️"
     }
 
-    fn project_patterns(&self) -> Vec<DetectionPattern> {
+    fn project_patterns(&self) -> Vec<ArtifactPattern> {
         vec![
-            DetectionPattern::DirectoryWithSibling {
-                dir_name: "build".to_string(),
-                sibling: "CMakeLists.txt".to_string(),
-            },
-            DetectionPattern::DirectoryWithSibling {
-                dir_name: "build".to_string(),
-                sibling: "Makefile".to_string(),
-            },
-            DetectionPattern::GlobPattern("cmake-build-*".to_string()),
-            DetectionPattern::DirectoryWithSibling {
-                dir_name: "out".to_string(),
-                sibling: "CMakeLists.txt".to_string(),
-            },
+            ArtifactPattern::new(
+                "C++",
+                ArtifactKind::Build,
+                DetectionPattern::AllOf(vec![
+                    DetectionPattern::DirectoryName("build".to_string()),
+                    DetectionPattern::AnyOf(vec![
+                        DetectionPattern::SiblingExists("CMakeLists.txt".to_string()),
+                        // With a hand-written Makefile, a `build` that has its own
+                        // Makefile is part of the build system, not its output
+                        DetectionPattern::AllOf(vec![
+                            DetectionPattern::SiblingExists("Makefile".to_string()),
+                            DetectionPattern::Not(Box::new(DetectionPattern::ContainsFile {
+                                file: "Makefile".to_string(),
+                                signature: None,
+                            })),
+                        ]),
+                    ]),
+                ]),
+            )
+            .ambiguous(),
+            ArtifactPattern::new(
+                "CMake",
+                ArtifactKind::Build,
+                DetectionPattern::GlobPattern("cmake-build-*".to_string()),
+            )
+            .regenerate("cmake --build"),
+            // Out-of-source CMake build trees, whatever they are called
+            ArtifactPattern::new(
+                "CMake",
+                ArtifactKind::Build,
+                DetectionPattern::ContainsFile {
+                    file: "CMakeCache.txt".to_string(),
+                    signature: None,
+                },
+            )
+            .regenerate("cmake --build"),
+            ArtifactPattern::new(
+                "CMake",
+                ArtifactKind::Build,
+                DetectionPattern::DirectoryWithSibling {
+                    dir_name: "out".to_string(),
+                    sibling: "CMakeLists.txt".to_string(),
+                },
+            )
+            .regenerate("cmake --build")
+            .ambiguous(),
+        ]
+    }
+
+    fn project_markers(&self) -> Vec<String> {
+        vec![
+            "CMakeLists.txt".to_string(),
+            "Makefile".to_string(),
+            "meson.build".to_string(),
         ]
     }
 
@@ -1,4 +1,7 @@
-use super::{DetectionPattern, GlobalCachePath, LanguageCleaner, OrphanedPackage};
+use super::{
+    ArtifactKind, ArtifactPattern, DetectionPattern, GlobalCachePath, LanguageCleaner,
+    OrphanedPackage,
+};
 use std::fs;
 
 pub struct JavaCleaner;
@@ -12,21 +15,37 @@ impl LanguageCleaner for JavaCleaner {
         "☕"
     }
 
-    fn project_patterns(&self) -> Vec<DetectionPattern> {
+    fn project_patterns(&self) -> Vec<ArtifactPattern> {
         vec![
-            DetectionPattern::DirectoryName(".gradle".to_string()),
-            DetectionPattern::DirectoryWithSibling {
-                dir_name: "build".to_string(),
-                sibling: "build.gradle".to_string(),
-            },
-            DetectionPattern::DirectoryWithSibling {
-                dir_name: "build".to_string(),
-                sibling: "build.gradle.kts".to_string(),
-            },
-            DetectionPattern::DirectoryWithSibling {
-                dir_name: "build".to_string(),
-                sibling: "pom.xml".to_string(),
-            },
+            ArtifactPattern::new(
+                "Gradle",
+                ArtifactKind::Cache,
+                DetectionPattern::DirectoryName(".gradle".to_string()),
+            ),
+            ArtifactPattern::new(
+                "Java",
+                ArtifactKind::Build,
+                DetectionPattern::AllOf(vec![
+                    DetectionPattern::DirectoryName("build".to_string()),
+                    DetectionPattern::AnyOf(vec![
+                        DetectionPattern::SiblingExists("build.gradle".to_string()),
+                        DetectionPattern::SiblingExists("build.gradle.kts".to_string()),
+                        DetectionPattern::SiblingExists("pom.xml".to_string()),
+                    ]),
+                ]),
+            )
+            .regenerate("gradle build")
+            .ambiguous(),
+        ]
+    }
+
+    fn project_markers(&self) -> Vec<String> {
+        vec![
+            "build.gradle".to_string(),
+            "build.gradle.kts".to_string(),
+            "settings.gradle".to_string(),
+            "settings.gradle.kts".to_string(),
+            "pom.xml".to_string(),
         ]
     }
 
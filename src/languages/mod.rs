@@ -1,5 +1,7 @@
-use std::path::PathBuf;
+use std::fmt;
+use std::path::{Path, PathBuf};
 
+pub mod cachedir;
 pub mod cpp;
 pub mod java;
 pub mod node;
@@ -12,8 +14,112 @@ pub enum DetectionPattern {
     DirectoryName(String),
     /// Match directory name with a required sibling file
     DirectoryWithSibling { dir_name: String, sibling: String },
-    /// Match glob pattern for directories (e.g., "*.egg-info")
+    /// Match a glob (e.g., "*.egg-info", "build-*-release", "{dist,out}")
+    /// against the directory name, or against the path relative to the
+    /// project root if it contains a `/` (e.g., "packages/*/dist")
     GlobPattern(String),
+    /// Match a regular expression against the whole directory name, or the
+    /// relative path if it contains a `/`, like `GlobPattern`
+    #[allow(dead_code)] // None of the built-in cleaners need one yet
+    Regex(String),
+    /// Match any directory containing a marker file (e.g., "pyvenv.cfg"),
+    /// optionally only if the file starts with a signature
+    ContainsFile {
+        file: String,
+        signature: Option<String>,
+    },
+    /// Match any directory with a file of this name next to it
+    SiblingExists(String),
+    /// Match any directory whose parent has this name
+    ParentName(String),
+    /// Match when at least one of the patterns matches
+    AnyOf(Vec<DetectionPattern>),
+    /// Match when every pattern matches (checked in order, so put cheap name
+    /// checks first)
+    AllOf(Vec<DetectionPattern>),
+    /// Match when the pattern doesn't; only useful inside `AllOf`
+    Not(Box<DetectionPattern>),
+}
+
+impl DetectionPattern {
+    /// A `.gitignore` line ignoring everything the pattern matches, when the
+    /// directory name alone decides (e.g., "__pycache__/", "*.egg-info/")
+    pub fn gitignore_rule(&self) -> Option<String> {
+        match self {
+            DetectionPattern::DirectoryName(name) => Some(format!("{}/", name)),
+            // gitignore has no `{a,b}` alternation
+            DetectionPattern::GlobPattern(glob) if !glob.contains(['/', '{']) => {
+                Some(format!("{}/", glob))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// What a detected directory holds
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum, serde::Serialize, serde::Deserialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum ArtifactKind {
+    /// Installed dependencies (node_modules, bower_components, ...)
+    Dependencies,
+    /// Build output (target, dist, build, ...)
+    Build,
+    /// Tool caches (__pycache__, .pytest_cache, CACHEDIR.TAG, ...)
+    Cache,
+    /// Virtual environments
+    Venv,
+}
+
+impl fmt::Display for ArtifactKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match self {
+            ArtifactKind::Dependencies => "dependency install",
+            ArtifactKind::Build => "build output",
+            ArtifactKind::Cache => "tool cache",
+            ArtifactKind::Venv => "virtual environment",
+        };
+        f.write_str(kind)
+    }
+}
+
+/// A detection pattern with what it finds
+#[derive(Debug, Clone)]
+pub struct ArtifactPattern {
+    pub pattern: DetectionPattern,
+    /// Display label (e.g., "Next.js", "Python venv")
+    pub label: String,
+    pub kind: ArtifactKind,
+    /// Command that recreates the directory (e.g., "npm ci")
+    pub regenerate: Option<String>,
+    /// The pattern can also match hand-written directories (e.g., `dist`)
+    pub ambiguous: bool,
+}
+
+impl ArtifactPattern {
+    pub fn new(label: &str, kind: ArtifactKind, pattern: DetectionPattern) -> ArtifactPattern {
+        ArtifactPattern {
+            pattern,
+            label: label.to_string(),
+            kind,
+            regenerate: None,
+            ambiguous: false,
+        }
+    }
+
+    /// Set the command that recreates the directory
+    pub fn regenerate(mut self, command: &str) -> ArtifactPattern {
+        self.regenerate = Some(command.to_string());
+        self
+    }
+
+    /// Mark the pattern as ambiguous: its matches need more evidence before
+    /// they are cleaned without asking
+    pub fn ambiguous(mut self) -> ArtifactPattern {
+        self.ambiguous = true;
+        self
+    }
 }
 
 // These types are defined for future features (global cache scanning, orphaned package detection)
@@ -40,7 +146,15 @@ pub trait LanguageCleaner: Send + Sync {
     fn icon(&self) -> &str;
 
     /// Return a list of directory names/patterns this module detects in project directories
-    fn project_patterns(&self) -> Vec<DetectionPattern>;
+    fn project_patterns(&self) -> Vec<ArtifactPattern>;
+
+    /// Return the file names that mark the root of a project (e.g., "package.json")
+    fn project_markers(&self) -> Vec<String>;
+
+    /// Optional: Extra details about a detected directory (e.g., a venv's interpreter)
+    fn describe(&self, _artifact: &Path) -> Option<String> {
+        None
+    }
 
     /// Return a list of global cache locations to scan
     #[allow(dead_code)]
@@ -61,6 +175,7 @@ pub fn get_all_cleaners() -> Vec<Box<dyn LanguageCleaner>> {
         Box::new(rust_lang::RustCleaner),
         Box::new(java::JavaCleaner),
         Box::new(cpp::CppCleaner),
+        Box::new(cachedir::CacheDirCleaner),
     ]
 }
 
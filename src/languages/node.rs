@@ -1,4 +1,7 @@
-use super::{DetectionPattern, GlobalCachePath, LanguageCleaner, OrphanedPackage};
+use super::{
+    ArtifactKind, ArtifactPattern, DetectionPattern, GlobalCachePath, LanguageCleaner,
+    OrphanedPackage,
+};
 use std::process::Command;
 
 pub struct NodeCleaner;
@@ -12,20 +15,64 @@ impl LanguageCleaner for NodeCleaner {
         "🟢"
     }
 
-    fn project_patterns(&self) -> Vec<DetectionPattern> {
+    fn project_patterns(&self) -> Vec<ArtifactPattern> {
         vec![
-            DetectionPattern::DirectoryName("node_modules".to_string()),
-            DetectionPattern::DirectoryName(".next".to_string()),
-            DetectionPattern::DirectoryName(".nuxt".to_string()),
-            DetectionPattern::DirectoryName(".parcel-cache".to_string()),
-            DetectionPattern::DirectoryName("bower_components".to_string()),
-            DetectionPattern::DirectoryWithSibling {
-                dir_name: "dist".to_string(),
-                sibling: "package.json".to_string(),
-            },
+            ArtifactPattern::new(
+                "Node.js",
+                ArtifactKind::Dependencies,
+                DetectionPattern::DirectoryName("node_modules".to_string()),
+            )
+            .regenerate("npm ci"),
+            ArtifactPattern::new(
+                "Next.js",
+                ArtifactKind::Build,
+                DetectionPattern::DirectoryName(".next".to_string()),
+            )
+            .regenerate("next build"),
+            ArtifactPattern::new(
+                "Nuxt",
+                ArtifactKind::Build,
+                DetectionPattern::DirectoryName(".nuxt".to_string()),
+            )
+            .regenerate("nuxt build"),
+            ArtifactPattern::new(
+                "Parcel",
+                ArtifactKind::Cache,
+                DetectionPattern::DirectoryName(".parcel-cache".to_string()),
+            ),
+            ArtifactPattern::new(
+                "Bower",
+                ArtifactKind::Dependencies,
+                DetectionPattern::DirectoryName("bower_components".to_string()),
+            )
+            .regenerate("bower install"),
+            // Packages Yarn PnP had to extract from its archives
+            ArtifactPattern::new(
+                "Yarn",
+                ArtifactKind::Dependencies,
+                DetectionPattern::AllOf(vec![
+                    DetectionPattern::DirectoryName("unplugged".to_string()),
+                    DetectionPattern::ParentName(".yarn".to_string()),
+                ]),
+            )
+            .regenerate("yarn install"),
+            ArtifactPattern::new(
+                "JavaScript",
+                ArtifactKind::Build,
+                DetectionPattern::DirectoryWithSibling {
+                    dir_name: "dist".to_string(),
+                    sibling: "package.json".to_string(),
+                },
+            )
+            .regenerate("npm run build")
+            .ambiguous(),
         ]
     }
 
+    fn project_markers(&self) -> Vec<String> {
+        vec!["package.json".to_string()]
+    }
+
     fn global_cache_paths(&self) -> Vec<GlobalCachePath> {
         let mut paths = Vec::new();
 
@@ -1,4 +1,9 @@
-use super::{DetectionPattern, GlobalCachePath, LanguageCleaner, OrphanedPackage};
+use super::{
+    ArtifactKind, ArtifactPattern, DetectionPattern, GlobalCachePath, LanguageCleaner,
+    OrphanedPackage,
+};
+use std::fs;
+use std::path::Path;
 use std::process::Command;
 
 pub struct PythonCleaner;
@@ -12,24 +17,103 @@ impl LanguageCleaner for PythonCleaner {
         "🐍"
     }
 
-    fn project_patterns(&self) -> Vec<DetectionPattern> {
+    fn project_patterns(&self) -> Vec<ArtifactPattern> {
         vec![
-            DetectionPattern::DirectoryName("__pycache__".to_string()),
-            DetectionPattern::DirectoryName(".venv".to_string()),
-            DetectionPattern::DirectoryName("venv".to_string()),
-            DetectionPattern::DirectoryName("env".to_string()),
-            DetectionPattern::DirectoryName(".tox".to_string()),
-            DetectionPattern::DirectoryName(".pytest_cache".to_string()),
-            DetectionPattern::DirectoryName(".mypy_cache".to_string()),
-            DetectionPattern::DirectoryName(".ruff_cache".to_string()),
-            DetectionPattern::GlobPattern("*.egg-info".to_string()),
-            DetectionPattern::DirectoryWithSibling {
-                dir_name: "dist".to_string(),
-                sibling: "setup.py".to_string(),
-            },
+            ArtifactPattern::new(
+                "Python",
+                ArtifactKind::Cache,
+                DetectionPattern::DirectoryName("__pycache__".to_string()),
+            ),
+            // Virtual environments, whatever they are called
+            ArtifactPattern::new(
+                "Python venv",
+                ArtifactKind::Venv,
+                DetectionPattern::ContainsFile {
+                    file: "pyvenv.cfg".to_string(),
+                    signature: None,
+                },
+            )
+            .regenerate("python -m venv"),
+            // Older virtualenvs have no pyvenv.cfg: only trust the usual names
+            // when they hold an interpreter, so a config dir or a Go package
+            // called `env` is left alone
+            ArtifactPattern::new(
+                "Python venv",
+                ArtifactKind::Venv,
+                DetectionPattern::AllOf(vec![
+                    DetectionPattern::AnyOf(vec![
+                        DetectionPattern::DirectoryName(".venv".to_string()),
+                        DetectionPattern::DirectoryName("venv".to_string()),
+                        DetectionPattern::DirectoryName("env".to_string()),
+                    ]),
+                    DetectionPattern::AnyOf(vec![
+                        DetectionPattern::ContainsFile {
+                            file: "bin/python".to_string(),
+                            signature: None,
+                        },
+                        DetectionPattern::ContainsFile {
+                            file: "Scripts/python.exe".to_string(),
+                            signature: None,
+                        },
+                    ]),
+                ]),
+            )
+            .regenerate("virtualenv"),
+            ArtifactPattern::new(
+                "Python tox",
+                ArtifactKind::Venv,
+                DetectionPattern::DirectoryName(".tox".to_string()),
+            )
+            .regenerate("tox"),
+            ArtifactPattern::new(
+                "Python pytest",
+                ArtifactKind::Cache,
+                DetectionPattern::DirectoryName(".pytest_cache".to_string()),
+            ),
+            ArtifactPattern::new(
+                "Python mypy",
+                ArtifactKind::Cache,
+                DetectionPattern::DirectoryName(".mypy_cache".to_string()),
+            ),
+            ArtifactPattern::new(
+                "Python ruff",
+                ArtifactKind::Cache,
+                DetectionPattern::DirectoryName(".ruff_cache".to_string()),
+            ),
+            ArtifactPattern::new(
+                "Python egg-info",
+                ArtifactKind::Build,
+                DetectionPattern::GlobPattern("*.egg-info".to_string()),
+            )
+            .regenerate("pip install -e ."),
+            ArtifactPattern::new(
+                "Python dist",
+                ArtifactKind::Build,
+                DetectionPattern::DirectoryWithSibling {
+                    dir_name: "dist".to_string(),
+                    sibling: "setup.py".to_string(),
+                },
+            )
+            .regenerate("python -m build")
+            .ambiguous(),
         ]
     }
 
+    fn project_markers(&self) -> Vec<String> {
+        vec![
+            "pyproject.toml".to_string(),
+            "setup.py".to_string(),
+            "setup.cfg".to_string(),
+            "requirements.txt".to_string(),
+            "Pipfile".to_string(),
+            "tox.ini".to_string(),
+        ]
+    }
+
+    fn describe(&self, artifact: &Path) -> Option<String> {
+        describe_venv(artifact)
+    }
+
     fn global_cache_paths(&self) -> Vec<GlobalCachePath> {
         let mut paths = Vec::new();
 
@@ -100,6 +184,45 @@ impl LanguageCleaner for PythonCleaner {
     }
 }
 
+/// Interpreter version of a virtual environment, and whether the base
+/// interpreter it was created from (the `home =` line) still exists
+fn describe_venv(dir: &Path) -> Option<String> {
+    let config = fs::read_to_string(dir.join("pyvenv.cfg")).ok()?;
+    let value = |key: &str| {
+        config.lines().find_map(|line| {
+            let (k, v) = line.split_once('=')?;
+            (k.trim() == key).then(|| v.trim().to_string())
+        })
+    };
+
+    // `venv` writes `version`, virtualenv and uv write `version_info`
+    let version = value("version").or_else(|| value("version_info"));
+    let mut details = Vec::new();
+    if let Some(version) = &version {
+        details.push(format!("Python {}", version));
+    }
+
+    if let Some(home) = value("home") {
+        let home = Path::new(&home);
+        let short_version = version.as_deref().and_then(|v| {
+            let mut parts = v.split('.');
+            Some(format!("{}.{}", parts.next()?, parts.next()?))
+        });
+        let names = match short_version {
+            Some(v) => vec![format!("python{}", v), "python.exe".to_string()],
+            None => ["python3", "python", "python.exe"]
+                .map(String::from)
+                .to_vec(),
+        };
+        let base_exists = names.iter().any(|name| home.join(name).exists());
+        if !base_exists {
+            details.push(format!("base interpreter missing from {}", home.display()));
+        }
+    }
+
+    (!details.is_empty()).then(|| details.join(", "))
+}
+
 /// Try running pip or pip3 to list installed packages
 fn try_pip_command() -> Option<String> {
     // Try pip first
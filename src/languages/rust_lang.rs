@@ -1,4 +1,7 @@
-use super::{DetectionPattern, GlobalCachePath, LanguageCleaner, OrphanedPackage};
+use super::{
+    ArtifactKind, ArtifactPattern, DetectionPattern, GlobalCachePath, LanguageCleaner,
+    OrphanedPackage,
+};
 use std::process::Command;
 
 pub struct RustCleaner;
@@ -12,11 +15,32 @@ impl LanguageCleaner for RustCleaner {
         "🦀"
     }
 
-    fn project_patterns(&self) -> Vec<DetectionPattern> {
-        vec![DetectionPattern::DirectoryWithSibling {
-            dir_name: "target".to_string(),
-            sibling: "Cargo.toml".to_string(),
-        }]
+    fn project_patterns(&self) -> Vec<ArtifactPattern> {
+        vec![
+            ArtifactPattern::new(
+                "Rust",
+                ArtifactKind::Build,
+                DetectionPattern::DirectoryWithSibling {
+                    dir_name: "target".to_string(),
+                    sibling: "Cargo.toml".to_string(),
+                },
+            )
+            .regenerate("cargo build"),
+            // Target dirs moved elsewhere with CARGO_TARGET_DIR or --target-dir
+            ArtifactPattern::new(
+                "Rust",
+                ArtifactKind::Build,
+                DetectionPattern::ContainsFile {
+                    file: ".rustc_info.json".to_string(),
+                    signature: None,
+                },
+            )
+            .regenerate("cargo build"),
+        ]
+    }
+
+    fn project_markers(&self) -> Vec<String> {
+        vec!["Cargo.toml".to_string()]
     }
 
     fn global_cache_paths(&self) -> Vec<GlobalCachePath> {
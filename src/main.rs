@@ -1,10 +1,28 @@
+mod cache;
+mod check;
 mod cleaner;
+mod confidence;
+mod config;
+mod deletion;
+mod filter;
+mod git;
+mod gitignore;
 mod languages;
+mod matcher;
+mod mounts;
+mod plan;
+mod project;
+mod quarantine;
 mod scanner;
+mod sizing;
+#[cfg(test)]
+mod testing;
+mod trash;
 mod utils;
 
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand};
 use std::path::PathBuf;
+use std::time::Duration;
 
 #[derive(Parser)]
 #[command(name = "sweepkit")]
@@ -15,23 +33,205 @@ struct Cli {
     command: Commands,
 }
 
+/// Options shared by every command that scans for dev dependency folders
+#[derive(Args)]
+struct ScanArgs {
+    /// Root directory to scan, repeatable (default: configured roots, or the
+    /// current directory)
+    #[arg(short, long)]
+    path: Vec<PathBuf>,
+
+    /// Filter by language/ecosystem (e.g., python, node, rust, java, cpp)
+    #[arg(short, long)]
+    language: Option<String>,
+
+    /// Number of worker threads, for scanning and deleting (default: one per
+    /// CPU; use 1-2 on spinning disks)
+    #[arg(short, long, default_value_t = 0)]
+    jobs: usize,
+
+    /// Don't read or update the persistent size cache
+    #[arg(long, default_value_t = false)]
+    no_cache: bool,
+
+    /// Maximum directory depth to descend below the root (0: only the root
+    /// itself)
+    #[arg(long)]
+    max_depth: Option<usize>,
+
+    /// Skip directories matching a glob; patterns with a `/` match the full
+    /// path (e.g. `~/.local/share`, `/mnt/*`), others the directory name (repeatable)
+    #[arg(short = 'x', long, value_parser = filter::parse_exclude)]
+    exclude: Vec<String>,
+
+    /// Only report folders at least this large (e.g., 100MB, 1GiB)
+    #[arg(long, value_parser = utils::parse_size, default_value = "0")]
+    min_size: u64,
+
+    /// Don't descend into hidden directories (artifacts like .venv are still found)
+    #[arg(long, default_value_t = false)]
+    skip_hidden: bool,
+
+    /// Only include projects not touched for at least this long (e.g., 30d, 12h, 2w)
+    #[arg(long, value_parser = filter::parse_duration)]
+    older_than: Option<Duration>,
+
+    /// Only include projects touched within this long (e.g., 7d)
+    #[arg(long, value_parser = filter::parse_duration)]
+    newer_than: Option<Duration>,
+
+    /// Don't cross into other filesystems (network and pseudo filesystems
+    /// are always skipped)
+    #[arg(long, default_value_t = false)]
+    one_file_system: bool,
+
+    /// Which directory symlinks to follow when scanning and sizing
+    #[arg(long, value_enum, default_value_t = sizing::SymlinkPolicy::Never)]
+    symlinks: sizing::SymlinkPolicy,
+
+    /// Only report these kinds of folders (repeatable)
+    #[arg(short, long, value_enum)]
+    kind: Vec<languages::ArtifactKind>,
+
+    /// Exit with an error (and never delete anything) if any entry could not
+    /// be read, instead of just warning
+    #[arg(long, default_value_t = false)]
+    strict: bool,
+}
+
+impl ScanArgs {
+    fn options(&self) -> scanner::ScanOptions {
+        scanner::ScanOptions {
+            jobs: self.jobs,
+            use_cache: !self.no_cache,
+            max_depth: self.max_depth,
+            exclude: self.exclude.clone(),
+            min_size: self.min_size,
+            skip_hidden: self.skip_hidden,
+            older_than: self.older_than,
+            newer_than: self.newer_than,
+            one_file_system: self.one_file_system,
+            symlinks: self.symlinks,
+            kinds: self.kind.clone(),
+        }
+    }
+
+    /// Roots to scan: `--path` arguments, else configured roots, else "."
+    fn roots(&self) -> Vec<PathBuf> {
+        let roots = if !self.path.is_empty() {
+            self.path.clone()
+        } else {
+            config::Config::load().roots()
+        };
+
+        if roots.is_empty() {
+            vec![PathBuf::from(".")]
+        } else {
+            scanner::dedup_roots(&roots)
+        }
+    }
+
+    /// Print the banner and run the scan
+    fn scan(&self) -> scanner::ScanReport {
+        let roots = self.roots();
+        let names: Vec<String> = roots.iter().map(|r| r.display().to_string()).collect();
+        println!("🔍 Scanning {} for dev dependencies...\n", names.join(", "));
+
+        let report = self.run(&roots);
+        utils::display_skipped_mounts(&report.skipped_mounts);
+        report
+    }
+
+    /// Run the scan without printing anything to stdout, for machine-readable
+    /// output
+    fn scan_quietly(&self) -> scanner::ScanReport {
+        self.run(&self.roots())
+    }
+
+    fn run(&self, roots: &[PathBuf]) -> scanner::ScanReport {
+        let options = self.options();
+        let progress = utils::ScanProgress::new();
+        let report = if let Some(lang) = &self.language {
+            scanner::scan_directory_filtered(roots, lang, &options, &progress)
+        } else {
+            scanner::scan_directory(roots, &options, &progress)
+        };
+        progress.finish();
+
+        utils::display_scan_errors(&report.errors);
+        report
+    }
+
+    /// Exit with an error under `--strict` if anything could not be read
+    fn enforce_strict(&self, errors: &[sizing::ScanError]) {
+        if self.strict && !errors.is_empty() {
+            eprintln!(
+                "❌ Scan incomplete: {} entries could not be read (--strict)",
+                errors.len()
+            );
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Options choosing what happens to cleaned folders
+#[derive(Args)]
+struct RemovalArgs {
+    /// Move folders to the trash instead of deleting them (default: the
+    /// `trash` setting of the config file)
+    #[arg(long, default_value_t = false, overrides_with = "no_trash")]
+    trash: bool,
+
+    /// Delete folders even if the config file enables the trash
+    #[arg(long, default_value_t = false, overrides_with = "trash")]
+    no_trash: bool,
+
+    /// Move folders to sweepkit's quarantine, from where `sweepkit undo`
+    /// restores them; they are purged after a week (see config)
+    #[arg(long, default_value_t = false, conflicts_with = "trash")]
+    quarantine: bool,
+
+    /// When deleting, make read-only directories you own writable and retry
+    /// (directories of other users are never changed)
+    #[arg(long, default_value_t = false)]
+    fix_permissions: bool,
+}
+
+impl RemovalArgs {
+    /// The chosen removal; quarantining first purges expired runs
+    fn removal(&self) -> cleaner::Removal {
+        let config = config::Config::load();
+        if self.quarantine {
+            let (purged, freed) = quarantine::purge_older_than(config.quarantine_expiry());
+            if purged > 0 {
+                println!(
+                    "🧹 Purged {} expired quarantine runs, reclaimed {}\n",
+                    purged,
+                    utils::format_size(freed)
+                );
+            }
+            cleaner::Removal::Quarantine
+        } else if self.trash || (config.trash && !self.no_trash) {
+            cleaner::Removal::Trash
+        } else {
+            cleaner::Removal::Delete {
+                fix_permissions: self.fix_permissions,
+            }
+        }
+    }
+}
+
 #[derive(Subcommand)]
 enum Commands {
     /// Scan for dev dependency folders
     Scan {
-        /// Root directory to scan (default: current directory)
-        #[arg(short, long, default_value = ".")]
-        path: PathBuf,
-
-        /// Filter by language/ecosystem (e.g., python, node, rust, java, cpp)
-        #[arg(short, long)]
-        language: Option<String>,
+        #[command(flatten)]
+        scan: ScanArgs,
     },
     /// Clean dev dependency folders interactively
     Clean {
-        /// Root directory to scan (default: current directory)
-        #[arg(short, long, default_value = ".")]
-        path: PathBuf,
+        #[command(flatten)]
+        scan: ScanArgs,
 
         /// Clean everything without confirmation
         #[arg(short, long, default_value_t = false)]
@@ -41,9 +241,79 @@ enum Commands {
         #[arg(short, long, default_value_t = false)]
         dry_run: bool,
 
-        /// Filter by language/ecosystem (e.g., python, node, rust, java, cpp)
-        #[arg(short = 'l', long)]
-        language: Option<String>,
+        /// With --all, also delete low-confidence matches
+        #[arg(long, default_value_t = false)]
+        include_uncertain: bool,
+
+        #[command(flatten)]
+        removal: RemovalArgs,
+    },
+    /// Write the folders to clean to a plan file, to review and apply later
+    Plan {
+        #[command(flatten)]
+        scan: ScanArgs,
+
+        /// Plan file to write
+        #[arg(short, long)]
+        output: PathBuf,
+
+        /// Plan everything without asking
+        #[arg(short, long, default_value_t = false)]
+        all: bool,
+
+        /// With --all, also plan low-confidence matches
+        #[arg(long, default_value_t = false)]
+        include_uncertain: bool,
+    },
+    /// Clean the folders of a plan file, skipping any that changed since
+    Apply {
+        /// Plan file written by `sweepkit plan`
+        plan: PathBuf,
+
+        /// Don't ask for confirmation
+        #[arg(short, long, default_value_t = false)]
+        yes: bool,
+
+        /// Number of folders removed at once (default: one per CPU; use 1-2
+        /// on spinning disks)
+        #[arg(short, long, default_value_t = 0)]
+        jobs: usize,
+
+        #[command(flatten)]
+        removal: RemovalArgs,
+    },
+    /// Restore the folders of a `clean --quarantine` run
+    Undo {
+        /// Run to restore (default: the latest one)
+        run_id: Option<String>,
+    },
+    /// Manage folders moved away by `clean --quarantine`
+    Quarantine {
+        #[command(subcommand)]
+        action: QuarantineAction,
+    },
+    /// Fail if artifact folders are committed to git (for CI)
+    Check {
+        #[command(flatten)]
+        scan: ScanArgs,
+
+        /// Output format
+        #[arg(long, value_enum, default_value_t = check::OutputFormat::Text)]
+        format: check::OutputFormat,
+
+        /// Also report ambiguous matches (dist, build, out), which are
+        /// sometimes committed on purpose
+        #[arg(long, default_value_t = false)]
+        include_uncertain: bool,
+    },
+    /// Propose .gitignore lines for detected folders git doesn't ignore yet
+    Gitignore {
+        #[command(flatten)]
+        scan: ScanArgs,
+
+        /// Append the proposed lines instead of only showing them
+        #[arg(short, long, default_value_t = false)]
+        write: bool,
     },
     /// List globally installed packages
     Global {
@@ -51,41 +321,55 @@ enum Commands {
         #[arg(short, long)]
         language: Option<String>,
     },
+    /// Manage the persistent size cache
+    CacheIndex {
+        #[command(subcommand)]
+        action: CacheAction,
+    },
+}
+
+#[derive(Subcommand)]
+enum QuarantineAction {
+    /// List quarantined runs
+    List,
+    /// Delete quarantined folders for good
+    Purge {
+        /// Only purge runs at least this old (e.g., 7d; default: everything)
+        #[arg(long, value_parser = filter::parse_duration)]
+        older_than: Option<Duration>,
+    },
+}
+
+#[derive(Subcommand)]
+enum CacheAction {
+    /// Remove entries for directories that are gone or have changed
+    Prune,
 }
 
 fn main() {
     let cli = Cli::parse();
 
     match cli.command {
-        Commands::Scan { path, language } => {
-            println!("🔍 Scanning {} for dev dependencies...\n", path.display());
-
-            let items = if let Some(lang) = language {
-                scanner::scan_directory_filtered(&path, &lang)
-            } else {
-                scanner::scan_directory(&path)
-            };
+        Commands::Scan { scan } => {
+            let report = scan.scan();
 
-            if items.is_empty() {
+            if report.items.is_empty() {
                 println!("✨ No dev dependency folders found!");
-                return;
+            } else {
+                utils::display_scan_results(&report.items);
             }
-
-            utils::display_scan_results(&items);
+            scan.enforce_strict(&report.errors);
         }
         Commands::Clean {
-            path,
+            scan,
             all,
             dry_run,
-            language,
+            include_uncertain,
+            removal,
         } => {
-            println!("🔍 Scanning {} for dev dependencies...\n", path.display());
-
-            let items = if let Some(lang) = language {
-                scanner::scan_directory_filtered(&path, &lang)
-            } else {
-                scanner::scan_directory(&path)
-            };
+            let scanner::ScanReport { items, errors, .. } = scan.scan();
+            // Before anything is deleted, and for dry runs alike
+            scan.enforce_strict(&errors);
 
             if items.is_empty() {
                 println!("✨ No dev dependency folders found!");
@@ -95,15 +379,187 @@ fn main() {
             if dry_run {
                 println!("🔍 DRY RUN - Nothing will be deleted\n");
                 utils::display_scan_results(&items);
-                let total_size: u64 = items.iter().map(|item| item.size).sum();
+                let total_size: u64 = items.iter().map(|item| item.reclaimable).sum();
                 println!(
                     "\n💾 Total space that would be reclaimed: {}",
                     utils::format_size(total_size)
                 );
             } else {
-                cleaner::clean_directories(items, all);
+                let options = cleaner::CleanOptions {
+                    all,
+                    include_uncertain,
+                    removal: removal.removal(),
+                    jobs: scan.jobs,
+                };
+                cleaner::clean_directories(items, options);
+            }
+        }
+        Commands::Plan {
+            scan,
+            output,
+            all,
+            include_uncertain,
+        } => {
+            let scanner::ScanReport { items, errors, .. } = scan.scan();
+            scan.enforce_strict(&errors);
+
+            let selected = cleaner::select_items(items, all, include_uncertain);
+            if selected.is_empty() {
+                return;
+            }
+
+            let plan = plan::Plan::new(&selected);
+            if let Err(e) = plan.save(&output) {
+                eprintln!("❌ Failed to write plan {}: {}", output.display(), e);
+                std::process::exit(1);
+            }
+            println!(
+                "\n📋 Wrote a plan for {} directories ({}) to {}",
+                plan.items.len(),
+                utils::format_size(plan.reclaimable()),
+                output.display()
+            );
+            println!(
+                "ℹ️  Review it, then run `sweepkit apply {}`",
+                output.display()
+            );
+        }
+        Commands::Apply {
+            plan,
+            yes,
+            jobs,
+            removal,
+        } => {
+            let plan = match plan::Plan::load(&plan) {
+                Ok(loaded) => loaded,
+                Err(e) => {
+                    eprintln!("❌ Failed to read plan {}: {}", plan.display(), e);
+                    std::process::exit(1);
+                }
+            };
+
+            let targets = plan.verified_targets();
+            if targets.is_empty() {
+                println!("✨ Nothing left to clean");
+                return;
+            }
+
+            println!("📋 Cleaning {} planned directories:", targets.len());
+            for target in &targets {
+                println!(
+                    "   {} - {}",
+                    utils::format_size(target.size),
+                    target.path.display()
+                );
+            }
+            let total_size: u64 = targets.iter().map(|target| target.size).sum();
+            println!(
+                "\n💾 Total space to be reclaimed: {}",
+                utils::format_size(total_size)
+            );
+
+            if !yes && !cleaner::confirm_deletion(targets.len()) {
+                return;
+            }
+            cleaner::remove_directories(&targets, removal.removal(), jobs);
+        }
+        Commands::Check {
+            scan,
+            format,
+            include_uncertain,
+        } => {
+            let report = match format {
+                check::OutputFormat::Text => scan.scan(),
+                check::OutputFormat::Json => scan.scan_quietly(),
+            };
+            let tracked = check::tracked_artifacts(&report.items, include_uncertain);
+            let unknown = check::unknown_artifacts(&report.items, include_uncertain);
+            check::report(&tracked, &unknown, &report.errors, format);
+
+            scan.enforce_strict(&report.errors);
+            if !tracked.is_empty() || !unknown.is_empty() {
+                std::process::exit(1);
             }
         }
+        Commands::Gitignore { scan, write } => {
+            let report = scan.scan();
+            scan.enforce_strict(&report.errors);
+
+            let changes = gitignore::propose(&report.items);
+            if changes.is_empty() {
+                println!("✨ Every detected folder is already gitignored or tracked");
+                return;
+            }
+
+            gitignore::display_diff(&changes);
+            if !write {
+                println!("\nℹ️  Run with --write to append these lines");
+                return;
+            }
+
+            println!();
+            for change in &changes {
+                match gitignore::apply(change) {
+                    Ok(()) => println!(
+                        "✅ Updated {} (+{} lines)",
+                        change.repo.join(&change.file).display(),
+                        change.rules.len()
+                    ),
+                    Err(e) => eprintln!(
+                        "❌ Failed to update {}: {}",
+                        change.repo.join(&change.file).display(),
+                        e
+                    ),
+                }
+            }
+        }
+        Commands::Undo { run_id } => {
+            let mut runs = quarantine::Run::load_all();
+            let run = match &run_id {
+                Some(id) => runs
+                    .iter()
+                    .position(|run| run.id == *id)
+                    .map(|idx| runs.remove(idx)),
+                None => runs.pop(),
+            };
+            let Some(run) = run else {
+                match run_id {
+                    Some(id) => {
+                        eprintln!("❌ No quarantine run {}", id);
+                        std::process::exit(1);
+                    }
+                    None => println!("✨ Nothing to undo"),
+                }
+                return;
+            };
+
+            let id = run.id.clone();
+            let (restored, failed) = run.restore();
+            println!("↩️  Restored {} directories from run {}", restored, id);
+            for (entry, e) in &failed {
+                eprintln!(
+                    "❌ Failed to restore {} to {}: {}",
+                    entry.quarantined.display(),
+                    entry.original.display(),
+                    e
+                );
+            }
+            if !failed.is_empty() {
+                std::process::exit(1);
+            }
+        }
+        Commands::Quarantine { action } => match action {
+            QuarantineAction::List => utils::display_quarantine(&quarantine::Run::load_all()),
+            QuarantineAction::Purge { older_than } => {
+                let (purged, freed) =
+                    quarantine::purge_older_than(older_than.unwrap_or(Duration::ZERO));
+                println!(
+                    "🧹 Purged {} quarantined runs, reclaimed {}",
+                    purged,
+                    utils::format_size(freed)
+                );
+            }
+        },
         Commands::Global { language } => {
             println!("🔍 Scanning for globally installed packages...\n");
 
@@ -149,5 +605,27 @@ fn main() {
                 println!("✨ No globally installed packages found!");
             }
         }
+        Commands::CacheIndex { action } => match action {
+            CacheAction::Prune => {
+                let Some(file) = cache::SizeCache::default_path() else {
+                    eprintln!("⚠️  No cache directory available on this system");
+                    return;
+                };
+
+                let size_cache = cache::SizeCache::load(file);
+                let removed = size_cache.prune();
+
+                if let Err(e) = size_cache.save() {
+                    eprintln!("❌ Failed to save size cache: {}", e);
+                    return;
+                }
+
+                println!(
+                    "🧹 Pruned {} stale entries, {} remaining",
+                    removed,
+                    size_cache.len()
+                );
+            }
+        },
     }
 }
@@ -0,0 +1,374 @@
+use crate::languages::DetectionPattern;
+use globset::{GlobBuilder, GlobMatcher};
+use regex::Regex;
+use std::collections::HashSet;
+use std::ffi::OsString;
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+
+/// A directory being checked against detection patterns
+pub struct Candidate<'a> {
+    /// Directory name
+    pub name: &'a str,
+    /// Path of the directory as walked
+    pub path: &'a Path,
+    /// Path relative to the enclosing project root (or the scan root when
+    /// there is no project above it)
+    pub relative: &'a Path,
+    /// Entry names of the parent directory, when it was read by the walk
+    pub siblings: Option<&'a HashSet<OsString>>,
+    /// Entry names of the directory itself, when it could be read
+    pub contents: Option<&'a HashSet<OsString>>,
+}
+
+impl Candidate<'_> {
+    /// Whether the parent directory has a file named `name`
+    ///
+    /// Names missing from the parent's entries are ruled out without a
+    /// syscall; only a listed name is checked to be a file.
+    fn has_sibling(&self, name: &str) -> bool {
+        listed(self.siblings, name) && has_sibling_file(self.path, name)
+    }
+
+    /// Whether the directory has a file at `name` (which may be nested, like
+    /// `bin/python`), starting with `signature` if given
+    ///
+    /// Only the first component of `name` is looked up in the listing.
+    fn has_file(&self, name: &str, signature: Option<&str>) -> bool {
+        listed(self.contents, name) && contains_file(self.path, name, signature)
+    }
+}
+
+/// A detection pattern, compiled once per scan
+pub enum Matcher {
+    Name(String),
+    WithSibling {
+        name: String,
+        sibling: String,
+    },
+    /// Matches the relative path instead of the name when `whole_path` is set
+    Glob {
+        glob: GlobMatcher,
+        whole_path: bool,
+    },
+    Regex {
+        regex: Regex,
+        whole_path: bool,
+    },
+    ContainsFile {
+        file: String,
+        signature: Option<String>,
+    },
+    Sibling(String),
+    ParentName(String),
+    AnyOf(Vec<Matcher>),
+    AllOf(Vec<Matcher>),
+    Not(Box<Matcher>),
+}
+
+impl Matcher {
+    /// Compile a pattern
+    ///
+    /// Globs and regexes containing a `/` are matched against the path
+    /// relative to the project root, others against the directory name.
+    pub fn compile(pattern: &DetectionPattern) -> Result<Matcher, String> {
+        let matcher = match pattern {
+            DetectionPattern::DirectoryName(name) => Matcher::Name(name.clone()),
+            DetectionPattern::DirectoryWithSibling { dir_name, sibling } => Matcher::WithSibling {
+                name: dir_name.clone(),
+                sibling: sibling.clone(),
+            },
+            DetectionPattern::GlobPattern(glob) => {
+                let whole_path = glob.contains('/');
+                let glob = GlobBuilder::new(glob)
+                    .literal_separator(true)
+                    .build()
+                    .map_err(|e| e.to_string())?;
+                Matcher::Glob {
+                    glob: glob.compile_matcher(),
+                    whole_path,
+                }
+            }
+            DetectionPattern::Regex(regex) => Matcher::Regex {
+                whole_path: regex.contains('/'),
+                // Like globs, a regex has to match the whole name or path
+                regex: Regex::new(&format!("^(?:{})$", regex)).map_err(|e| e.to_string())?,
+            },
+            DetectionPattern::ContainsFile { file, signature } => Matcher::ContainsFile {
+                file: file.clone(),
+                signature: signature.clone(),
+            },
+            DetectionPattern::SiblingExists(sibling) => Matcher::Sibling(sibling.clone()),
+            DetectionPattern::ParentName(name) => Matcher::ParentName(name.clone()),
+            DetectionPattern::AnyOf(patterns) => Matcher::AnyOf(compile_all(patterns)?),
+            DetectionPattern::AllOf(patterns) => Matcher::AllOf(compile_all(patterns)?),
+            DetectionPattern::Not(pattern) => Matcher::Not(Box::new(Matcher::compile(pattern)?)),
+        };
+        Ok(matcher)
+    }
+
+    pub fn matches(&self, candidate: &Candidate) -> bool {
+        match self {
+            Matcher::Name(name) => candidate.name == name,
+            Matcher::WithSibling { name, sibling } => {
+                candidate.name == name && candidate.has_sibling(sibling)
+            }
+            Matcher::Glob { glob, whole_path } => {
+                if *whole_path {
+                    glob.is_match(candidate.relative)
+                } else {
+                    glob.is_match(candidate.name)
+                }
+            }
+            Matcher::Regex { regex, whole_path } => {
+                if *whole_path {
+                    candidate
+                        .relative
+                        .to_str()
+                        .is_some_and(|path| regex.is_match(path))
+                } else {
+                    regex.is_match(candidate.name)
+                }
+            }
+            Matcher::ContainsFile { file, signature } => {
+                candidate.has_file(file, signature.as_deref())
+            }
+            Matcher::Sibling(sibling) => candidate.has_sibling(sibling),
+            Matcher::ParentName(name) => candidate
+                .path
+                .parent()
+                .and_then(|parent| parent.file_name())
+                .is_some_and(|parent| parent == name.as_str()),
+            Matcher::AnyOf(matchers) => matchers.iter().any(|m| m.matches(candidate)),
+            Matcher::AllOf(matchers) => matchers.iter().all(|m| m.matches(candidate)),
+            Matcher::Not(matcher) => !matcher.matches(candidate),
+        }
+    }
+}
+
+fn compile_all(patterns: &[DetectionPattern]) -> Result<Vec<Matcher>, String> {
+    patterns.iter().map(Matcher::compile).collect()
+}
+
+/// Whether the first component of `name` may be in a directory listing
+/// (anything may be when the directory wasn't listed)
+fn listed(names: Option<&HashSet<OsString>>, name: &str) -> bool {
+    let Some(first) = Path::new(name).components().next() else {
+        return false;
+    };
+    names.map_or(true, |names| names.contains(first.as_os_str()))
+}
+
+/// Check if a sibling file exists (in the parent directory)
+fn has_sibling_file(dir: &Path, filename: &str) -> bool {
+    if let Some(parent) = dir.parent() {
+        parent.join(filename).is_file()
+    } else {
+        false
+    }
+}
+
+/// Check if a directory contains a file, optionally starting with `signature`
+pub fn contains_file(dir: &Path, filename: &str, signature: Option<&str>) -> bool {
+    let path = dir.join(filename);
+    let Some(signature) = signature else {
+        return path.is_file();
+    };
+
+    let mut head = vec![0; signature.len()];
+    fs::File::open(&path)
+        .and_then(|mut file| file.read_exact(&mut head))
+        .is_ok_and(|_| head == signature.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn compile(pattern: DetectionPattern) -> Matcher {
+        Matcher::compile(&pattern).unwrap()
+    }
+
+    fn glob(glob: &str) -> Matcher {
+        compile(DetectionPattern::GlobPattern(glob.to_string()))
+    }
+
+    fn regex(regex: &str) -> Matcher {
+        compile(DetectionPattern::Regex(regex.to_string()))
+    }
+
+    /// A candidate for `relative` inside a project at `/project`, listed
+    /// with no siblings or contents
+    fn matches(matcher: &Matcher, relative: &str) -> bool {
+        let relative = Path::new(relative);
+        let path = Path::new("/project").join(relative);
+        let empty = HashSet::new();
+        matcher.matches(&Candidate {
+            name: relative.file_name().unwrap().to_str().unwrap(),
+            path: &path,
+            relative,
+            siblings: Some(&empty),
+            contents: Some(&empty),
+        })
+    }
+
+    fn names(names: &[&str]) -> HashSet<OsString> {
+        names.iter().map(OsString::from).collect()
+    }
+
+    #[test]
+    fn glob_without_slash_matches_the_name() {
+        let matcher = glob("*.egg-info");
+        assert!(matches(&matcher, "foo.egg-info"));
+        assert!(matches(&matcher, "src/foo.egg-info"));
+        assert!(!matches(&matcher, "foo.egg-info.bak"));
+
+        let matcher = glob("{dist,out}");
+        assert!(matches(&matcher, "packages/a/out"));
+        assert!(!matches(&matcher, "output"));
+    }
+
+    #[test]
+    fn glob_with_slash_matches_the_relative_path() {
+        let matcher = glob("packages/*/dist");
+        assert!(matches(&matcher, "packages/web/dist"));
+        assert!(!matches(&matcher, "dist"));
+        // `*` doesn't cross directories
+        assert!(!matches(&matcher, "packages/web/app/dist"));
+        assert!(!matches(&matcher, "vendor/packages/web/dist"));
+    }
+
+    #[test]
+    fn regex_must_match_the_whole_name() {
+        let matcher = regex("build-[0-9]+");
+        assert!(matches(&matcher, "build-42"));
+        assert!(!matches(&matcher, "build-42-old"));
+        assert!(!matches(&matcher, "my-build-42"));
+
+        // Alternatives are anchored as a whole, not just the first and last
+        let matcher = regex("out|dist");
+        assert!(matches(&matcher, "out"));
+        assert!(!matches(&matcher, "outdated"));
+        assert!(!matches(&matcher, "undist"));
+    }
+
+    #[test]
+    fn regex_with_slash_matches_the_whole_relative_path() {
+        let matcher = regex("apps/[^/]+/build");
+        assert!(matches(&matcher, "apps/web/build"));
+        assert!(!matches(&matcher, "build"));
+        assert!(!matches(&matcher, "apps/web/build/cache"));
+        assert!(!matches(&matcher, "old/apps/web/build"));
+    }
+
+    #[test]
+    fn invalid_patterns_are_rejected() {
+        assert!(Matcher::compile(&DetectionPattern::GlobPattern("a[".to_string())).is_err());
+        assert!(Matcher::compile(&DetectionPattern::Regex("(a".to_string())).is_err());
+    }
+
+    #[test]
+    fn combinators() {
+        let yarn = compile(DetectionPattern::AllOf(vec![
+            DetectionPattern::DirectoryName("unplugged".to_string()),
+            DetectionPattern::ParentName(".yarn".to_string()),
+        ]));
+        assert!(matches(&yarn, ".yarn/unplugged"));
+        assert!(!matches(&yarn, "unplugged"));
+        assert!(!matches(&yarn, ".yarn/cache"));
+
+        let build = compile(DetectionPattern::AllOf(vec![
+            DetectionPattern::DirectoryName("build".to_string()),
+            DetectionPattern::Not(Box::new(DetectionPattern::ParentName("src".to_string()))),
+        ]));
+        assert!(matches(&build, "build"));
+        assert!(!matches(&build, "src/build"));
+
+        let either = compile(DetectionPattern::AnyOf(vec![
+            DetectionPattern::DirectoryName("out".to_string()),
+            DetectionPattern::GlobPattern("dist-*".to_string()),
+        ]));
+        assert!(matches(&either, "out"));
+        assert!(matches(&either, "dist-web"));
+        assert!(!matches(&either, "dist"));
+    }
+
+    #[test]
+    fn marker_files_are_looked_up_in_listings() {
+        let project = std::env::temp_dir().join(format!("sweepkit-matcher-{}", std::process::id()));
+        let dir = project.join("stuff");
+        fs::create_dir_all(dir.join("pyvenv.cfg")).unwrap();
+        fs::write(
+            dir.join("CACHEDIR.TAG"),
+            "Signature: 8a477f597d28d172789f06886806bc55",
+        )
+        .unwrap();
+        fs::create_dir_all(dir.join("bin")).unwrap();
+        fs::write(dir.join("bin/python"), "").unwrap();
+        fs::write(project.join("CMakeLists.txt"), "").unwrap();
+        fs::create_dir_all(project.join("pom.xml")).unwrap();
+
+        let contents = names(&["pyvenv.cfg", "CACHEDIR.TAG", "bin"]);
+        let siblings = names(&["stuff", "CMakeLists.txt", "pom.xml"]);
+        let listed = Candidate {
+            name: "stuff",
+            path: &dir,
+            relative: Path::new("stuff"),
+            siblings: Some(&siblings),
+            contents: Some(&contents),
+        };
+        // Without listings every marker file is looked up on disk
+        let unlisted = Candidate {
+            siblings: None,
+            contents: None,
+            ..listed
+        };
+        // Listings are trusted: what they lack isn't looked up
+        let empty = HashSet::new();
+        let stale = Candidate {
+            siblings: Some(&empty),
+            contents: Some(&empty),
+            ..listed
+        };
+
+        let tagged = |signature: &str| {
+            compile(DetectionPattern::ContainsFile {
+                file: "CACHEDIR.TAG".to_string(),
+                signature: Some(signature.to_string()),
+            })
+        };
+        let venv = compile(DetectionPattern::ContainsFile {
+            file: "pyvenv.cfg".to_string(),
+            signature: None,
+        });
+        let cmake = compile(DetectionPattern::SiblingExists(
+            "CMakeLists.txt".to_string(),
+        ));
+        let maven = compile(DetectionPattern::SiblingExists("pom.xml".to_string()));
+        // Nested markers are listed by their first component
+        let interpreter = compile(DetectionPattern::ContainsFile {
+            file: "bin/python".to_string(),
+            signature: None,
+        });
+        let windows_interpreter = compile(DetectionPattern::ContainsFile {
+            file: "Scripts/python.exe".to_string(),
+            signature: None,
+        });
+        for candidate in [&listed, &unlisted] {
+            assert!(tagged("Signature: 8a477f597d28d172789f06886806bc55").matches(candidate));
+            assert!(!tagged("Signature: 0000").matches(candidate));
+            // A directory with the marker's name isn't the marker
+            assert!(!venv.matches(candidate));
+            assert!(!maven.matches(candidate));
+            assert!(cmake.matches(candidate));
+            assert!(interpreter.matches(candidate));
+            assert!(!windows_interpreter.matches(candidate));
+        }
+        assert!(!tagged("Signature: 8a477f597d28d172789f06886806bc55").matches(&stale));
+        assert!(!cmake.matches(&stale));
+        assert!(!interpreter.matches(&stale));
+
+        fs::remove_dir_all(&project).unwrap();
+    }
+}
@@ -0,0 +1,211 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// Filesystem types that live on another machine
+const NETWORK_FS: &[&str] = &[
+    "nfs",
+    "nfs4",
+    "cifs",
+    "smb3",
+    "smbfs",
+    "ncpfs",
+    "afs",
+    "9p",
+    "ceph",
+    "glusterfs",
+    "lustre",
+    "gpfs",
+    "davfs",
+    "fuse.sshfs",
+    "fuse.rclone",
+    "fuse.s3fs",
+    "fuse.gcsfuse",
+    "fuse.davfs2",
+    "fuse.glusterfs",
+    "fuse.gvfsd-fuse",
+];
+
+/// Filesystem types generated by the kernel or a daemon, with no real files
+const PSEUDO_FS: &[&str] = &[
+    "proc",
+    "sysfs",
+    "devtmpfs",
+    "devpts",
+    "cgroup",
+    "cgroup2",
+    "securityfs",
+    "debugfs",
+    "tracefs",
+    "pstore",
+    "bpf",
+    "configfs",
+    "fusectl",
+    "mqueue",
+    "hugetlbfs",
+    "autofs",
+    "binfmt_misc",
+    "efivarfs",
+    "rpc_pipefs",
+    "nsfs",
+    "selinuxfs",
+    "fuse.portal",
+];
+
+/// Layered filesystems used by container runtimes
+const CONTAINER_FS: &[&str] = &["overlay", "aufs"];
+
+/// Why a mount is skipped during a scan
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SkipReason {
+    Network,
+    Pseudo,
+    Container,
+    OtherFilesystem,
+}
+
+impl fmt::Display for SkipReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let reason = match self {
+            SkipReason::Network => "network filesystem",
+            SkipReason::Pseudo => "pseudo filesystem",
+            SkipReason::Container => "container filesystem",
+            SkipReason::OtherFilesystem => "other filesystem (--one-file-system)",
+        };
+        f.write_str(reason)
+    }
+}
+
+/// One entry of the mount table
+#[derive(Debug, Clone)]
+pub struct Mount {
+    pub mount_point: PathBuf,
+    pub fs_type: String,
+}
+
+impl Mount {
+    /// Whether scans should stay out of this mount, and why
+    pub fn skip_reason(&self) -> Option<SkipReason> {
+        let fs_type = self.fs_type.as_str();
+        if NETWORK_FS.contains(&fs_type) {
+            Some(SkipReason::Network)
+        } else if PSEUDO_FS.contains(&fs_type) {
+            Some(SkipReason::Pseudo)
+        } else if CONTAINER_FS.contains(&fs_type) {
+            // Merged views of image layers that also exist on the host disk
+            Some(SkipReason::Container)
+        } else {
+            None
+        }
+    }
+}
+
+/// A mount the scanner did not enter
+#[derive(Debug, Clone)]
+pub struct SkippedMount {
+    pub path: PathBuf,
+    pub fs_type: String,
+    pub reason: SkipReason,
+}
+
+/// Mount points of the running system, keyed by path
+#[derive(Debug, Default)]
+pub struct MountTable {
+    mounts: HashMap<PathBuf, Mount>,
+}
+
+impl MountTable {
+    /// Read the mount table (only available on Linux; empty elsewhere)
+    pub fn load() -> MountTable {
+        let mounts = std::fs::read_to_string("/proc/self/mountinfo")
+            .map(|contents| {
+                contents
+                    .lines()
+                    .filter_map(parse_mountinfo_line)
+                    .map(|mount| (mount.mount_point.clone(), mount))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        MountTable { mounts }
+    }
+
+    /// The mount whose mount point is exactly `path` (an absolute, canonical path)
+    pub fn get(&self, path: &Path) -> Option<&Mount> {
+        self.mounts.get(path)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.mounts.is_empty()
+    }
+}
+
+/// Parse one line of `/proc/self/mountinfo`
+///
+/// Format: `id parent major:minor root mount-point options [optional...] - fstype source super-options`
+fn parse_mountinfo_line(line: &str) -> Option<Mount> {
+    let mut fields = line.split(' ');
+    let mount_point = unescape(fields.nth(4)?);
+    let fs_type = fields.skip_while(|f| *f != "-").nth(1)?;
+
+    Some(Mount {
+        mount_point: PathBuf::from(mount_point),
+        fs_type: fs_type.to_string(),
+    })
+}
+
+/// Decode the octal escapes (`\040` for space, ...) used in mountinfo paths
+fn unescape(field: &str) -> String {
+    let bytes = field.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if bytes[i] == b'\\' && i + 4 <= bytes.len() {
+            let octal = std::str::from_utf8(&bytes[i + 1..i + 4]).ok();
+            if let Some(value) = octal.and_then(|o| u8::from_str_radix(o, 8).ok()) {
+                out.push(value);
+                i += 4;
+                continue;
+            }
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mountinfo_lines() {
+        let nfs = "36 35 98:0 /export /mnt/data rw,noatime master:1 - nfs4 server:/export rw";
+        let mount = parse_mountinfo_line(nfs).unwrap();
+        assert_eq!(mount.mount_point, Path::new("/mnt/data"));
+        assert_eq!(mount.fs_type, "nfs4");
+        assert_eq!(mount.skip_reason(), Some(SkipReason::Network));
+
+        // No optional fields
+        let tmpfs = "40 25 0:35 / /tmp rw,nosuid - tmpfs tmpfs rw";
+        let mount = parse_mountinfo_line(tmpfs).unwrap();
+        assert_eq!(mount.mount_point, Path::new("/tmp"));
+        assert_eq!(mount.skip_reason(), None);
+
+        assert!(parse_mountinfo_line("").is_none());
+        assert!(parse_mountinfo_line("36 35 98:0 / /mnt rw master:1").is_none());
+    }
+
+    #[test]
+    fn mount_points_are_unescaped() {
+        let line = r"50 25 0:40 / /media/My\040Disk rw - ext4 /dev/sdb1 rw";
+        let mount = parse_mountinfo_line(line).unwrap();
+        assert_eq!(mount.mount_point, Path::new("/media/My Disk"));
+
+        assert_eq!(unescape(r"tab\011and\134slash"), "tab\tand\\slash");
+        // Not octal escapes: kept as is
+        assert_eq!(unescape(r"a\09b\777\04"), r"a\09b\777\04");
+    }
+}
@@ -0,0 +1,289 @@
+use crate::cleaner::Target;
+use crate::git::{self, GitStatus};
+use crate::languages::ArtifactKind;
+use crate::scanner::FoundItem;
+use crate::sizing::DirStamp;
+use crate::utils;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Format of plan files, bumped on incompatible changes
+const PLAN_VERSION: u32 = 2;
+
+/// Folders selected for cleaning, written by `sweepkit plan` and carried
+/// out later by `sweepkit apply`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Plan {
+    pub version: u32,
+    /// Seconds since the Unix epoch
+    pub created: u64,
+    pub items: Vec<PlannedItem>,
+}
+
+/// One folder of a plan, with what it looked like when planned
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PlannedItem {
+    /// Absolute path
+    pub path: PathBuf,
+    pub ecosystem: String,
+    pub label: String,
+    pub kind: ArtifactKind,
+    pub size: u64,
+    pub reclaimable: u64,
+    /// Every directory of the folder, with paths relative to it (the folder
+    /// itself is ""); only the link itself for a symlinked artifact
+    pub dirs: Vec<DirStamp>,
+}
+
+impl PlannedItem {
+    fn from_item(item: &FoundItem) -> Option<PlannedItem> {
+        let path = utils::absolute_keep_link(&item.path).ok()?;
+        let stamp = DirStamp::read(&path)?;
+
+        // Deleting a symlinked artifact only removes the link
+        let dirs = if item.link_target.is_some() || item.dirs.is_empty() {
+            vec![DirStamp {
+                path: PathBuf::new(),
+                ..stamp
+            }]
+        } else {
+            item.dirs
+                .iter()
+                .map(|dir| {
+                    Some(DirStamp {
+                        path: dir.path.strip_prefix(&item.path).ok()?.to_path_buf(),
+                        ..dir.clone()
+                    })
+                })
+                .collect::<Option<Vec<_>>>()?
+        };
+
+        Some(PlannedItem {
+            path,
+            ecosystem: item.ecosystem.clone(),
+            label: item.label.clone(),
+            kind: item.kind,
+            size: item.size,
+            reclaimable: item.reclaimable,
+            dirs,
+        })
+    }
+
+    /// Check that the folder is still the one that was planned
+    ///
+    /// Every directory of the tree must still have the same inode and mtime,
+    /// which any file added, removed or renamed anywhere in it changes.
+    pub fn verify(&self) -> Result<(), &'static str> {
+        let Some(current) = DirStamp::read(&self.path) else {
+            return Err("it is gone");
+        };
+        let replaced = self
+            .dirs
+            .iter()
+            .any(|stamp| stamp.path.as_os_str().is_empty() && stamp.inode != current.inode);
+        if replaced {
+            return Err("it was replaced since the plan was made");
+        }
+
+        let unchanged = self.dirs.par_iter().all(|stamp| {
+            DirStamp::read(&self.path.join(&stamp.path)).is_some_and(|current| {
+                current.mtime_ns == stamp.mtime_ns && current.inode == stamp.inode
+            })
+        });
+        if unchanged {
+            Ok(())
+        } else {
+            Err("it was modified since the plan was made")
+        }
+    }
+}
+
+impl Plan {
+    /// Plan the cleaning of `items`
+    pub fn new(items: &[FoundItem]) -> Plan {
+        let items = items
+            .iter()
+            .filter_map(|item| {
+                let planned = PlannedItem::from_item(item);
+                if planned.is_none() {
+                    eprintln!("⚠️  Leaving out {}: it is gone", item.path.display());
+                }
+                planned
+            })
+            .collect();
+
+        Plan {
+            version: PLAN_VERSION,
+            created: utils::unix_now(),
+            items,
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Plan, String> {
+        let data = fs::read(path).map_err(|e| e.to_string())?;
+        let plan: Plan = serde_json::from_slice(&data).map_err(|e| e.to_string())?;
+        if plan.version != PLAN_VERSION {
+            return Err(format!(
+                "unsupported plan version {} (expected {})",
+                plan.version, PLAN_VERSION
+            ));
+        }
+        Ok(plan)
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let mut data = serde_json::to_vec_pretty(self)?;
+        data.push(b'\n');
+        fs::write(path, data)
+    }
+
+    /// Folders still as planned and holding nothing tracked by git,
+    /// reporting the others
+    pub fn verified_targets(&self) -> Vec<Target> {
+        let unchanged: Vec<&PlannedItem> = self
+            .items
+            .iter()
+            .filter(|item| match item.verify() {
+                Ok(()) => true,
+                Err(reason) => {
+                    println!("⏭️  Skipping {}: {}", item.path.display(), reason);
+                    false
+                }
+            })
+            .collect();
+
+        // Files may have been committed since the plan was made
+        let paths: Vec<PathBuf> = unchanged.iter().map(|item| item.path.clone()).collect();
+        unchanged
+            .into_iter()
+            .zip(git::statuses(&paths))
+            .filter_map(|(item, git)| match git {
+                GitStatus::Tracked(_) => {
+                    println!("⏭️  Skipping {}: it holds {}", item.path.display(), git);
+                    None
+                }
+                GitStatus::Unknown => {
+                    println!(
+                        "⏭️  Skipping {}: git couldn't tell whether it is tracked",
+                        item.path.display()
+                    );
+                    None
+                }
+                _ => Some(Target {
+                    path: item.path.clone(),
+                    size: item.reclaimable,
+                }),
+            })
+            .collect()
+    }
+
+    pub fn reclaimable(&self) -> u64 {
+        self.items.iter().map(|item| item.reclaimable).sum()
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::scanner::ScanOptions;
+    use crate::testing::{backdate_dirs, scan, test_dir};
+    use std::process::Command;
+
+    /// A Node.js project with a nested dependency tree
+    fn project(root: &Path) -> PathBuf {
+        let project = root.join("app");
+        fs::create_dir_all(project.join("node_modules/pkg/lib")).unwrap();
+        fs::write(project.join("package.json"), "{}\n").unwrap();
+        fs::write(
+            project.join("node_modules/pkg/index.js"),
+            "exports.a = 1;\n",
+        )
+        .unwrap();
+        fs::write(
+            project.join("node_modules/pkg/lib/deep.js"),
+            "exports.b = 2;\n",
+        )
+        .unwrap();
+        project
+    }
+
+    /// Plan the cleaning of everything below `root`, as it is now
+    fn plan(root: &Path) -> Plan {
+        // Changes made right after planning must show in the timestamps
+        backdate_dirs(root, 1);
+        let plan = Plan::new(&scan(root, &ScanOptions::default()));
+        assert_eq!(plan.items.len(), 1);
+        plan
+    }
+
+    #[test]
+    fn planned_folders_are_verified_before_cleaning() {
+        let root = test_dir("plan-verify");
+        let modules = project(&root).join("node_modules");
+
+        let unchanged = plan(&root);
+        assert_eq!(unchanged.items[0].path, modules);
+        assert_eq!(unchanged.items[0].dirs.len(), 3);
+        assert_eq!(unchanged.items[0].verify(), Ok(()));
+        let targets = unchanged.verified_targets();
+        assert_eq!(targets.len(), 1);
+        assert_eq!(targets[0].path, modules);
+
+        // A file added anywhere in the tree, however deep
+        let modified = plan(&root);
+        fs::write(modules.join("pkg/lib/new.js"), "").unwrap();
+        assert_eq!(
+            modified.items[0].verify(),
+            Err("it was modified since the plan was made")
+        );
+        assert!(modified.verified_targets().is_empty());
+
+        // Another folder in its place, whatever it holds
+        let replaced = plan(&root);
+        fs::rename(&modules, root.join("app/old_modules")).unwrap();
+        fs::create_dir_all(modules.join("pkg/lib")).unwrap();
+        assert_eq!(
+            replaced.items[0].verify(),
+            Err("it was replaced since the plan was made")
+        );
+
+        fs::remove_dir_all(&modules).unwrap();
+        assert_eq!(replaced.items[0].verify(), Err("it is gone"));
+        assert!(replaced.verified_targets().is_empty());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn folders_tracked_since_planning_are_skipped() {
+        let root = test_dir("plan-git");
+        let project = project(&root);
+        let git = |args: &[&str]| {
+            Command::new("git")
+                .arg("-C")
+                .arg(&project)
+                .args(args)
+                .output()
+                .is_ok_and(|output| output.status.success())
+        };
+        if !git(&["init", "-q"]) {
+            // Without git every folder of a repository is skipped anyway
+            eprintln!("git is not available, skipping");
+            return fs::remove_dir_all(&root).unwrap();
+        }
+
+        let plan = plan(&root);
+        assert_eq!(plan.verified_targets().len(), 1);
+
+        // Staged, not committed: the index is all that counts, and the
+        // folder's own timestamps don't change
+        assert!(git(&["add", "--force", "node_modules/pkg/index.js"]));
+        assert_eq!(plan.items[0].verify(), Ok(()));
+        assert!(plan.verified_targets().is_empty());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,142 @@
+use crate::filter::ExcludeSet;
+use crate::languages;
+use crate::mounts::MountTable;
+use crate::sizing;
+use rayon::prelude::*;
+use std::collections::{HashMap, HashSet};
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Markers that make a directory a project root for every ecosystem
+const COMMON_MARKERS: &[&str] = &[".git", ".hg", ".svn"];
+
+/// Finds the project an artifact belongs to
+///
+/// The project root is the nearest ancestor containing a manifest from one of
+/// the cleaners (`package.json`, `Cargo.toml`, ...) or a VCS directory. The
+/// search never leaves the scan root, so a stray `.git` in a home directory
+/// can't turn the whole home into one project.
+pub struct ProjectLocator {
+    markers: HashSet<OsString>,
+    has_marker: HashMap<PathBuf, bool>,
+}
+
+/// File names that make a directory a project root
+///
+/// Always every cleaner's markers, even when scanning one language.
+pub fn project_markers() -> HashSet<OsString> {
+    languages::get_all_cleaners()
+        .iter()
+        .flat_map(|cleaner| cleaner.project_markers())
+        .map(OsString::from)
+        .chain(COMMON_MARKERS.iter().map(OsString::from))
+        .collect()
+}
+
+impl ProjectLocator {
+    pub fn new() -> ProjectLocator {
+        ProjectLocator {
+            markers: project_markers(),
+            has_marker: HashMap::new(),
+        }
+    }
+
+    fn is_project_dir(&mut self, dir: &Path) -> bool {
+        if let Some(&known) = self.has_marker.get(dir) {
+            return known;
+        }
+
+        let found = fs::read_dir(dir)
+            .map(|entries| {
+                entries
+                    .filter_map(|e| e.ok())
+                    .any(|entry| self.markers.contains(&entry.file_name()))
+            })
+            .unwrap_or(false);
+
+        self.has_marker.insert(dir.to_path_buf(), found);
+        found
+    }
+
+    /// Project root for an artifact, falling back to its parent directory
+    pub fn project_root(&mut self, artifact: &Path, scan_root: &Path) -> PathBuf {
+        let parent = artifact.parent().unwrap_or(artifact);
+
+        for dir in parent.ancestors() {
+            if !dir.starts_with(scan_root) {
+                break;
+            }
+            if self.is_project_dir(dir) {
+                return dir.to_path_buf();
+            }
+        }
+
+        parent.to_path_buf()
+    }
+}
+
+/// What a walk of a project must stay out of, as the scan itself does
+pub struct WalkLimits<'a> {
+    /// Directories in it (the artifacts found by the scan)
+    pub skip: &'a HashSet<PathBuf>,
+    pub exclude: &'a ExcludeSet,
+    pub mounts: &'a MountTable,
+    /// Device of the scan root, with `--one-file-system`
+    pub device: Option<u64>,
+}
+
+/// Newest modification time (ns since epoch) of the files in a project
+///
+/// Artifacts, VCS metadata, excluded directories and mounts the scan doesn't
+/// enter are left out, so only the project's own sources count as activity.
+pub fn newest_source_mtime(dir: &Path, limits: &WalkLimits) -> u64 {
+    // Mount points are looked up by canonical path
+    let canonical = fs::canonicalize(dir).unwrap_or_else(|_| dir.to_path_buf());
+    newest_mtime_below(dir, &canonical, limits)
+}
+
+fn newest_mtime_below(dir: &Path, canonical: &Path, limits: &WalkLimits) -> u64 {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return 0;
+    };
+
+    let mut newest = 0;
+    let mut subdirs = Vec::new();
+
+    for entry in entries.filter_map(|e| e.ok()) {
+        let Ok(file_type) = entry.file_type() else {
+            continue;
+        };
+
+        if file_type.is_dir() {
+            let path = entry.path();
+            let is_vcs = COMMON_MARKERS.iter().any(|m| entry.file_name() == *m);
+            if is_vcs || limits.skip.contains(&path) || limits.exclude.is_excluded(&path) {
+                continue;
+            }
+            // Symlinks are never followed, so this stays canonical
+            let canonical = canonical.join(entry.file_name());
+            let skipped_mount = limits
+                .mounts
+                .get(&canonical)
+                .is_some_and(|mount| mount.skip_reason().is_some());
+            let other_fs = limits.device.is_some()
+                && entry.metadata().ok().and_then(|m| sizing::device(&m)) != limits.device;
+            if !skipped_mount && !other_fs {
+                subdirs.push((path, canonical));
+            }
+        } else if file_type.is_file() {
+            if let Ok(metadata) = entry.metadata() {
+                newest = newest.max(sizing::mtime_ns(&metadata));
+            }
+        }
+    }
+
+    subdirs
+        .par_iter()
+        .map(|(subdir, canonical)| newest_mtime_below(subdir, canonical, limits))
+        .max()
+        .unwrap_or(0)
+        .max(newest)
+}
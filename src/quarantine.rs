@@ -0,0 +1,390 @@
+use crate::deletion;
+use crate::sizing;
+use crate::trash;
+use crate::utils;
+use serde::{Deserialize, Serialize};
+use std::ffi::{OsStr, OsString};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// How long quarantined folders are kept unless configured otherwise
+pub const DEFAULT_EXPIRY: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// A folder moved to the quarantine
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Entry {
+    /// Where it was, as an absolute path
+    pub original: PathBuf,
+    /// Where it is now
+    pub quarantined: PathBuf,
+    /// Bytes freed once it is purged
+    pub size: u64,
+}
+
+/// Journal of one `clean --quarantine` run, kept until the run is undone or
+/// purged
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Run {
+    pub id: String,
+    /// Seconds since the Unix epoch
+    pub created: u64,
+    pub entries: Vec<Entry>,
+}
+
+/// Sweepkit's data directory (`~/.local/share/sweepkit` on Linux)
+fn data_dir() -> io::Result<PathBuf> {
+    dirs::data_dir()
+        .map(|dir| dir.join("sweepkit"))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no data directory"))
+}
+
+fn journal_dir() -> io::Result<PathBuf> {
+    Ok(data_dir()?.join("journal"))
+}
+
+/// Quarantine area for a folder, on the folder's own filesystem so moving
+/// it there is a rename: sweepkit's data directory when it is on the same
+/// filesystem, else `.sweepkit-quarantine-$uid` at the top of the folder's
+/// own filesystem
+fn area_for(path: &Path) -> io::Result<PathBuf> {
+    let home_area = data_dir()?.join("quarantine");
+    let device = fs::symlink_metadata(path)
+        .ok()
+        .and_then(|metadata| sizing::device(&metadata));
+
+    match device {
+        Some(device) if trash::device_of_nearest(&home_area) != Some(device) => {
+            let top = trash::top_dir(path, device);
+            Ok(top.join(format!(".sweepkit-quarantine-{}", utils::uid())))
+        }
+        _ => Ok(home_area),
+    }
+}
+
+/// Places holding folders set aside by `clean --quarantine` or `--trash`
+///
+/// Set-aside folders keep their marker files, so scans have to stay out of
+/// these places or would report (and delete) them again.
+pub struct SetAside {
+    /// The home quarantine area and trash, as given and canonical
+    dirs: Vec<PathBuf>,
+    /// Base for relative scan paths
+    cwd: PathBuf,
+}
+
+impl SetAside {
+    pub fn load() -> SetAside {
+        let home_dirs = [
+            data_dir().map(|dir| dir.join("quarantine")).ok(),
+            dirs::data_dir().map(|dir| dir.join("Trash")),
+        ];
+        let mut dirs = Vec::new();
+        for dir in home_dirs.into_iter().flatten() {
+            if let Ok(canonical) = fs::canonicalize(&dir) {
+                dirs.push(canonical);
+            }
+            dirs.push(dir);
+        }
+
+        SetAside {
+            dirs,
+            cwd: std::env::current_dir().unwrap_or_default(),
+        }
+    }
+
+    /// Whether `dir` is one of the places, judging by its name (quarantine
+    /// areas and trashes at the top of other filesystems) or its path
+    pub fn is_set_aside(&self, dir: &Path) -> bool {
+        dir.file_name().is_some_and(is_area_name) || self.dirs.contains(&self.cwd.join(dir))
+    }
+
+    /// Whether `path` is one of the places or inside one
+    pub fn holds(&self, path: &Path) -> bool {
+        self.cwd
+            .join(path)
+            .ancestors()
+            .any(|dir| self.is_set_aside(dir))
+    }
+}
+
+/// Name of a quarantine area or trash directory, of any user
+fn is_area_name(name: &OsStr) -> bool {
+    let name = name.to_string_lossy();
+    name == ".Trash" || name.starts_with(".Trash-") || name.starts_with(".sweepkit-quarantine-")
+}
+
+impl Run {
+    /// Start a run, named after the current time, and claim its journal
+    pub fn start() -> io::Result<Run> {
+        let journal = journal_dir()?;
+        trash::create_private_dir(&journal)?;
+
+        let stamp = utils::local_timestamp().replace(':', "-");
+        for n in 1.. {
+            let id = if n == 1 {
+                stamp.clone()
+            } else {
+                format!("{}.{}", stamp, n)
+            };
+            let claimed = fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(journal.join(format!("{}.json", id)));
+            match claimed {
+                Ok(_) => {
+                    let run = Run {
+                        id,
+                        created: utils::unix_now(),
+                        entries: Vec::new(),
+                    };
+                    run.save()?;
+                    return Ok(run);
+                }
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+                Err(e) => return Err(e),
+            }
+        }
+        unreachable!()
+    }
+
+    /// Every run still in the journal, oldest first
+    pub fn load_all() -> Vec<Run> {
+        let Ok(entries) = journal_dir().and_then(fs::read_dir) else {
+            return Vec::new();
+        };
+
+        let mut runs: Vec<Run> = entries
+            .flatten()
+            .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "json"))
+            .filter_map(|entry| {
+                let data = fs::read(entry.path()).ok()?;
+                match serde_json::from_slice(&data) {
+                    Ok(run) => Some(run),
+                    Err(e) => {
+                        eprintln!(
+                            "⚠️  Ignoring unreadable journal {}: {}",
+                            entry.path().display(),
+                            e
+                        );
+                        None
+                    }
+                }
+            })
+            .collect();
+        runs.sort_by(|a, b| a.created.cmp(&b.created).then_with(|| a.id.cmp(&b.id)));
+        runs
+    }
+
+    fn journal_path(&self) -> io::Result<PathBuf> {
+        Ok(journal_dir()?.join(format!("{}.json", self.id)))
+    }
+
+    fn save(&self) -> io::Result<()> {
+        let path = self.journal_path()?;
+        // Write a temporary file and rename it, so a crash never leaves a
+        // half-written journal behind
+        let data = serde_json::to_vec_pretty(self)?;
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, data)?;
+        fs::rename(tmp, path)
+    }
+
+    /// When the run was made
+    pub fn created_at(&self) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(self.created)
+    }
+
+    /// Bytes held by the run
+    pub fn size(&self) -> u64 {
+        self.entries.iter().map(|entry| entry.size).sum()
+    }
+
+    /// Move a folder into the quarantine and record it in the journal
+    pub fn quarantine(&mut self, path: &Path, size: u64) -> io::Result<()> {
+        let original = utils::absolute_keep_link(path)?;
+        let name = original.file_name().unwrap_or_default();
+
+        let area = area_for(&original)?;
+        let run_dir = area.join(&self.id);
+        // The area may be in a shared place where anyone could have made it
+        trash::create_owned_dir(&area)
+            .and_then(|()| trash::create_owned_dir(&run_dir))
+            .map_err(|e| {
+                io::Error::new(
+                    e.kind(),
+                    format!("can't use the quarantine {}: {}", area.display(), e),
+                )
+            })?;
+
+        // Number the folders: several runs of `clean` see many `node_modules`
+        let mut file_name = OsString::from(format!("{}-", self.entries.len() + 1));
+        file_name.push(name);
+        let quarantined = run_dir.join(file_name);
+        fs::rename(&original, &quarantined)?;
+
+        self.entries.push(Entry {
+            original,
+            quarantined,
+            size,
+        });
+        self.save()
+    }
+
+    /// Drop the journal of a run that moved nothing
+    pub fn finish(self) -> io::Result<()> {
+        if self.entries.is_empty() {
+            fs::remove_file(self.journal_path()?)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Move every folder back where it was
+    ///
+    /// Folders whose original place is taken again stay in the quarantine
+    /// (and in the journal) and are returned with the reason.
+    pub fn restore(mut self) -> (usize, Vec<(Entry, io::Error)>) {
+        let dirs = self.run_dirs();
+        let mut failed = Vec::new();
+        let mut restored = 0;
+
+        for entry in std::mem::take(&mut self.entries) {
+            match restore_entry(&entry) {
+                Ok(()) => restored += 1,
+                Err(e) => failed.push((entry, e)),
+            }
+        }
+
+        self.entries = failed.iter().map(|(entry, _)| entry.clone()).collect();
+        self.close(dirs);
+        (restored, failed)
+    }
+
+    /// Delete every folder of the run for good
+    pub fn purge(mut self) -> (u64, Vec<(Entry, io::Error)>) {
+        let dirs = self.run_dirs();
+        let mut failed = Vec::new();
+        let mut freed = 0;
+
+        for entry in std::mem::take(&mut self.entries) {
+            // The user already chose to delete these: read-only directories
+            // they own must not keep them in the quarantine forever
+            match deletion::delete_directory(&entry.quarantined, true, &|_| {}) {
+                Ok(()) => freed += entry.size,
+                // Already gone: nothing left to purge
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+                Err(e) => failed.push((entry, e)),
+            }
+        }
+
+        self.entries = failed.iter().map(|(entry, _)| entry.clone()).collect();
+        self.close(dirs);
+        (freed, failed)
+    }
+
+    /// Save what is left of the run, or forget it once it is empty
+    ///
+    /// `dirs` are the run's directories in the quarantine areas, removed
+    /// when they end up empty.
+    fn close(self, dirs: Vec<PathBuf>) {
+        for dir in dirs {
+            let _ = fs::remove_dir(dir);
+        }
+
+        let result = if self.entries.is_empty() {
+            self.journal_path().and_then(fs::remove_file)
+        } else {
+            self.save()
+        };
+        if let Err(e) = result {
+            eprintln!("⚠️  Failed to update the journal of run {}: {}", self.id, e);
+        }
+    }
+
+    /// Directories of the run in the quarantine areas it used
+    fn run_dirs(&self) -> Vec<PathBuf> {
+        let mut dirs: Vec<PathBuf> = self
+            .entries
+            .iter()
+            .filter_map(|entry| entry.quarantined.parent())
+            .map(Path::to_path_buf)
+            .collect();
+        dirs.sort();
+        dirs.dedup();
+        dirs
+    }
+}
+
+fn restore_entry(entry: &Entry) -> io::Result<()> {
+    if fs::symlink_metadata(&entry.original).is_ok() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            "something else is there now",
+        ));
+    }
+    if let Some(parent) = entry.original.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::rename(&entry.quarantined, &entry.original)
+}
+
+/// Purge runs made at least `older_than` ago
+///
+/// Returns how many runs were purged and the bytes freed.
+pub fn purge_older_than(older_than: Duration) -> (usize, u64) {
+    let cutoff = SystemTime::now()
+        .checked_sub(older_than)
+        .unwrap_or(UNIX_EPOCH);
+    let mut purged = 0;
+    let mut freed = 0;
+
+    for run in Run::load_all() {
+        if run.created_at() > cutoff {
+            continue;
+        }
+        let id = run.id.clone();
+        let (bytes, failed) = run.purge();
+        freed += bytes;
+        if failed.is_empty() {
+            purged += 1;
+        }
+        for (entry, e) in failed {
+            eprintln!(
+                "❌ Failed to purge {} (run {}): {}",
+                entry.quarantined.display(),
+                id,
+                e
+            );
+        }
+    }
+
+    (purged, freed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set_aside_places_are_recognised() {
+        let set_aside = SetAside {
+            dirs: vec![PathBuf::from("/home/me/.local/share/sweepkit/quarantine")],
+            cwd: PathBuf::from("/home/me"),
+        };
+
+        assert!(set_aside.is_set_aside(Path::new(".local/share/sweepkit/quarantine")));
+        assert!(set_aside.is_set_aside(Path::new("/mnt/usb/.sweepkit-quarantine-1000")));
+        assert!(set_aside.is_set_aside(Path::new("/mnt/usb/.Trash-1000")));
+        assert!(set_aside.is_set_aside(Path::new("/mnt/usb/.Trash")));
+        assert!(!set_aside.is_set_aside(Path::new("/home/me/.local/share/sweepkit")));
+        assert!(!set_aside.is_set_aside(Path::new("/home/me/Trash")));
+
+        assert!(set_aside.holds(Path::new(
+            "/home/me/.local/share/sweepkit/quarantine/run/1-node_modules"
+        )));
+        assert!(set_aside.holds(Path::new("/mnt/usb/.Trash-1000/files/target")));
+        assert!(!set_aside.holds(Path::new("/home/me/work/node_modules")));
+    }
+}
@@ -1,138 +1,797 @@
-use crate::languages::{self, DetectionPattern, LanguageCleaner};
+use crate::cache::SizeCache;
+use crate::confidence::{self, Confidence, Evidence};
+use crate::filter::ExcludeSet;
+use crate::git::GitStatus;
+use crate::languages::{self, ArtifactKind, ArtifactPattern, LanguageCleaner};
+use crate::matcher::{Candidate, Matcher};
+use crate::mounts::{MountTable, SkipReason, SkippedMount};
+use crate::project::{self, ProjectLocator};
+use crate::quarantine::SetAside;
+use crate::sizing::{self, DirSize, DirStamp, ScanError, SizeOptions, SymlinkPolicy};
+use rayon::prelude::*;
+use std::collections::{HashMap, HashSet};
+use std::ffi::OsString;
+use std::fs;
 use std::path::{Path, PathBuf};
-use walkdir::WalkDir;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
 
 #[derive(Debug, Clone)]
 pub struct FoundItem {
     pub path: PathBuf,
+    /// Scan root the item was found under
+    pub root: PathBuf,
     pub ecosystem: String,
     pub icon: String,
+    /// What was found, more specific than the ecosystem (e.g., "Next.js")
+    pub label: String,
+    pub kind: ArtifactKind,
+    /// Command that recreates the directory
+    pub regenerate: Option<String>,
+    /// Apparent size (sum of file lengths, each hardlinked file counted once)
     pub size: u64,
+    /// Allocated size on disk, with hardlinks deduplicated across the scan
+    pub disk_size: u64,
+    /// Allocated bytes that deleting this item would actually free
+    pub reclaimable: u64,
+    /// Newest modification time of anything inside the item
+    pub modified: Option<SystemTime>,
+    /// Newest modification time of the owning project's sources
+    pub project_modified: Option<SystemTime>,
+    /// Where the item points to, when the artifact itself is a symlink
+    pub link_target: Option<PathBuf>,
+    /// Some entries inside could not be read, so the sizes are undercounted
+    pub incomplete: bool,
+    /// Extra information from the cleaner (e.g., a venv's Python version)
+    pub details: Option<String>,
+    /// Matched by a pattern that can also hit hand-written directories
+    pub ambiguous: bool,
+    /// `.gitignore` line covering every directory the pattern matches, if any
+    pub ignore_rule: Option<String>,
+    pub git: GitStatus,
+    pub confidence: Confidence,
+    /// What the confidence is based on
+    pub evidence: Vec<Evidence>,
+    /// Every directory of the sized tree, to tell later whether it changed
+    pub dirs: Vec<DirStamp>,
 }
 
-/// Calculate directory size recursively
-fn calculate_dir_size(path: &Path) -> u64 {
-    WalkDir::new(path)
-        .into_iter()
-        .filter_map(|entry| entry.ok())
-        .filter_map(|entry| entry.metadata().ok())
-        .filter(|metadata| metadata.is_file())
-        .map(|metadata| metadata.len())
-        .sum()
+impl FoundItem {
+    /// When the item or its project was last touched
+    pub fn last_active(&self) -> Option<SystemTime> {
+        self.modified.max(self.project_modified)
+    }
 }
 
-/// Check if a sibling file exists (in the parent directory)
-fn has_sibling_file(dir: &Path, filename: &str) -> bool {
-    if let Some(parent) = dir.parent() {
-        parent.join(filename).exists()
-    } else {
-        false
-    }
-}
-
-/// Check if directory matches a pattern and return ecosystem info
-fn check_pattern(
-    dir_name: &str,
-    full_path: &Path,
-    pattern: &DetectionPattern,
-    cleaner: &dyn LanguageCleaner,
-) -> Option<(String, String)> {
-    match pattern {
-        DetectionPattern::DirectoryName(name) => {
-            if dir_name == name {
-                Some((cleaner.name().to_string(), cleaner.icon().to_string()))
-            } else {
-                None
+/// Options controlling how a scan is performed
+#[derive(Debug, Clone, Default)]
+pub struct ScanOptions {
+    /// Number of worker threads (0 = one per available CPU)
+    pub jobs: usize,
+    /// Reuse and update the persistent size cache
+    pub use_cache: bool,
+    /// Don't look deeper than this many directories below the root (0 only
+    /// checks the root itself)
+    pub max_depth: Option<usize>,
+    /// Glob patterns for directories that are never walked
+    pub exclude: Vec<String>,
+    /// Only report items at least this large (apparent size, in bytes)
+    pub min_size: u64,
+    /// Don't descend into hidden directories (artifacts like `.venv` still match)
+    pub skip_hidden: bool,
+    /// Only report items whose project has been inactive for at least this long
+    pub older_than: Option<Duration>,
+    /// Only report items whose project was active within this long
+    pub newer_than: Option<Duration>,
+    /// Stay on the filesystem of each scan root
+    pub one_file_system: bool,
+    /// Which directory symlinks are followed while scanning and sizing
+    pub symlinks: SymlinkPolicy,
+    /// Only report these kinds of artifacts (all when empty)
+    pub kinds: Vec<ArtifactKind>,
+}
+
+impl ScanOptions {
+    /// Whether items are kept depending on when their project was active,
+    /// which is only known once the scan is done
+    fn filters_by_age(&self) -> bool {
+        self.older_than.is_some() || self.newer_than.is_some()
+    }
+
+    /// Whether an item passes the age filters (`min_size` is applied as
+    /// soon as an item is sized)
+    fn keeps(&self, item: &FoundItem, now: SystemTime) -> bool {
+        // Items with no timestamps at all count as infinitely old
+        let age = item
+            .last_active()
+            .map(|t| now.duration_since(t).unwrap_or_default());
+        if let Some(min_age) = self.older_than {
+            if age.is_some_and(|age| age < min_age) {
+                return false;
             }
         }
-        DetectionPattern::DirectoryWithSibling {
-            dir_name: dn,
-            sibling,
-        } => {
-            if dir_name == dn && has_sibling_file(full_path, sibling) {
-                Some((cleaner.name().to_string(), cleaner.icon().to_string()))
-            } else {
-                None
+        if let Some(max_age) = self.newer_than {
+            if age.map_or(true, |age| age > max_age) {
+                return false;
             }
         }
-        DetectionPattern::GlobPattern(glob_pattern) => {
-            // Simple glob matching for patterns like "*.egg-info" or "cmake-build-*"
-            if let Some(suffix) = glob_pattern.strip_prefix('*') {
-                if dir_name.ends_with(suffix) {
-                    return Some((cleaner.name().to_string(), cleaner.icon().to_string()));
-                }
-            } else if let Some(prefix) = glob_pattern.strip_suffix('*') {
-                if dir_name.starts_with(prefix) {
-                    return Some((cleaner.name().to_string(), cleaner.icon().to_string()));
-                }
+
+        true
+    }
+}
+
+/// A cleaner's pattern, compiled for matching
+struct Rule<'a> {
+    cleaner: &'a dyn LanguageCleaner,
+    artifact: ArtifactPattern,
+    matcher: Matcher,
+}
+
+/// Find the first rule matching a directory
+///
+/// Cleaners are tried in registration order and the first match wins, so a
+/// directory is only ever attributed to one ecosystem.
+fn match_directory<'r, 'a>(candidate: &Candidate, rules: &'r [Rule<'a>]) -> Option<&'r Rule<'a>> {
+    rules.iter().find(|rule| rule.matcher.matches(candidate))
+}
+
+/// Compile every cleaner's patterns, dropping (with a warning) invalid ones
+fn compile_patterns(cleaners: &[Box<dyn LanguageCleaner>]) -> Vec<Rule<'_>> {
+    cleaners
+        .iter()
+        .flat_map(|cleaner| {
+            cleaner
+                .project_patterns()
+                .into_iter()
+                .filter_map(|artifact| match Matcher::compile(&artifact.pattern) {
+                    Ok(matcher) => Some(Rule {
+                        cleaner: &**cleaner,
+                        artifact,
+                        matcher,
+                    }),
+                    Err(e) => {
+                        eprintln!(
+                            "⚠️  Ignoring invalid {} pattern {:?}: {}",
+                            cleaner.name(),
+                            artifact.pattern,
+                            e
+                        );
+                        None
+                    }
+                })
+        })
+        .collect()
+}
+
+/// Receives live updates while a scan runs
+///
+/// Callbacks are invoked from worker threads as soon as something happens, so
+/// results can be shown before the scan (and sizing of large items) finishes.
+pub trait ScanObserver: Sync {
+    /// A directory was read during the walk
+    fn dir_visited(&self) {}
+
+    /// A directory matched a pattern and is about to be sized
+    fn matched(&self, _path: &Path, _ecosystem: &str, _icon: &str) {}
+
+    /// A matched directory was sized, whether it is reported or not
+    fn sized(&self, _path: &Path) {}
+
+    /// An item will be in the report
+    ///
+    /// Called right after sizing, or with age filters once the whole scan is
+    /// done, since they depend on project activity. The item's `disk_size`
+    /// and project activity are only filled in by then.
+    fn found(&self, _item: &FoundItem) {}
+}
+
+/// Everything a scan produced
+#[derive(Debug, Default)]
+pub struct ScanReport {
+    pub items: Vec<FoundItem>,
+    /// Mount points that were not entered, in path order
+    pub skipped_mounts: Vec<SkippedMount>,
+    /// Entries that could not be read while walking or sizing, in path order
+    pub errors: Vec<ScanError>,
+}
+
+/// Where a walk started, with what's needed to spot mount points below it
+struct ScanRoot {
+    /// Root given by the user, recorded on found items
+    root: PathBuf,
+    /// Start of this walk: the root itself, or a followed symlink target
+    path: PathBuf,
+    canonical: PathBuf,
+    dev: Option<u64>,
+}
+
+/// Shared state for one scan, borrowed by every traversal task
+struct ScanContext<'a> {
+    rules: Vec<Rule<'a>>,
+    /// File names that make a directory a project root
+    markers: HashSet<OsString>,
+    found_items: Mutex<Vec<(FoundItem, DirSize)>>,
+    /// Every matched directory, including those left out of the report
+    matched: Mutex<HashSet<PathBuf>>,
+    skipped_mounts: Mutex<Vec<SkippedMount>>,
+    errors: Mutex<Vec<ScanError>>,
+    /// Symlink targets already walked, so cycles and duplicate links are cut
+    followed: Mutex<HashSet<PathBuf>>,
+    cache: Option<SizeCache>,
+    exclude: ExcludeSet,
+    /// Quarantine areas and trashes, never walked whatever the options
+    set_aside: SetAside,
+    mounts: MountTable,
+    size_options: SizeOptions,
+    options: &'a ScanOptions,
+    observer: &'a dyn ScanObserver,
+}
+
+impl ScanContext<'_> {
+    fn report_errors(&self, errors: impl IntoIterator<Item = ScanError>) {
+        if let Ok(mut all) = self.errors.lock() {
+            all.extend(errors);
+        }
+    }
+
+    /// Size a directory, reusing the cached result when its tree is unchanged
+    fn size_of(&self, path: &Path) -> DirSize {
+        let Some(cache) = &self.cache else {
+            return sizing::calculate_dir_size(path, &self.size_options);
+        };
+
+        cache.lookup(path, &self.size_options).unwrap_or_else(|| {
+            let size = sizing::calculate_dir_size(path, &self.size_options);
+            cache.store(path, &self.size_options, &size);
+            size
+        })
+    }
+
+    /// Resolve a symlinked directory found during the walk, if it should be
+    /// followed and hasn't been already
+    ///
+    /// Targets inside a scan root are walked directly, so only links leading
+    /// out of every root are ever followed.
+    fn follow_link(&self, link: &Path, root: &ScanRoot) -> Option<PathBuf> {
+        let roots: Vec<&Path> = self
+            .size_options
+            .roots
+            .iter()
+            .map(|r| r.as_path())
+            .collect();
+        let target = self.size_options.follow(link, &roots)?;
+
+        if self.options.one_file_system {
+            let dev = fs::metadata(&target).ok().and_then(|m| sizing::device(&m));
+            if dev != root.dev {
+                return None;
             }
+        }
+
+        let first_visit = self
+            .followed
+            .lock()
+            .map(|mut followed| followed.insert(target.clone()))
+            .unwrap_or(false);
+        first_visit.then_some(target)
+    }
+
+    /// Check whether a subdirectory is a mount point the scan must not enter
+    ///
+    /// Network, pseudo and container filesystems are always skipped; any other
+    /// filesystem only with `--one-file-system`.
+    fn skipped_mount(&self, entry: &fs::DirEntry, root: &ScanRoot) -> Option<SkippedMount> {
+        let path = entry.path();
+        let mount = if self.mounts.is_empty() {
             None
+        } else {
+            // Symlinks are never followed, so this is the canonical path
+            let absolute = root.canonical.join(path.strip_prefix(&root.path).ok()?);
+            self.mounts.get(&absolute)
+        };
+
+        let reason = mount.and_then(|m| m.skip_reason()).or_else(|| {
+            let other_fs = self.options.one_file_system
+                && entry.metadata().ok().and_then(|m| sizing::device(&m)) != root.dev;
+            other_fs.then_some(SkipReason::OtherFilesystem)
+        })?;
+
+        Some(SkippedMount {
+            path,
+            fs_type: mount.map_or_else(|| "unknown".to_string(), |m| m.fs_type.clone()),
+            reason,
+        })
+    }
+
+    /// Size a matched directory and record it
+    ///
+    /// For a symlinked artifact the target is sized, but nothing is
+    /// reclaimable: deleting the item only removes the link.
+    fn record(&self, path: PathBuf, root: &Path, rule: &Rule, link_target: Option<PathBuf>) {
+        if let Ok(mut matched) = self.matched.lock() {
+            matched.insert(path.clone());
+        }
+        // Other kinds are still pruned from the walk, just never sized
+        if !self.options.kinds.is_empty() && !self.options.kinds.contains(&rule.artifact.kind) {
+            return;
+        }
+
+        let cleaner = rule.cleaner;
+        let (ecosystem, icon) = (cleaner.name().to_string(), cleaner.icon().to_string());
+        self.observer.matched(&path, &ecosystem, &icon);
+        let mut dir_size = self.size_of(link_target.as_deref().unwrap_or(&path));
+        let errors = std::mem::take(&mut dir_size.errors);
+        let incomplete = !errors.is_empty();
+        self.report_errors(errors);
+
+        let size = dir_size.apparent_size();
+        self.observer.sized(&path);
+        if size == 0 || size < self.options.min_size {
+            return;
+        }
+
+        let reclaimable = if link_target.is_some() {
+            0
+        } else {
+            dir_size.reclaimable_size()
+        };
+        let item = FoundItem {
+            details: cleaner.describe(link_target.as_deref().unwrap_or(&path)),
+            path,
+            root: root.to_path_buf(),
+            ecosystem,
+            icon,
+            label: rule.artifact.label.clone(),
+            kind: rule.artifact.kind,
+            regenerate: rule.artifact.regenerate.clone(),
+            ambiguous: rule.artifact.ambiguous,
+            ignore_rule: rule.artifact.pattern.gitignore_rule(),
+            // Set once the scan is done, see `confidence::assess`
+            git: GitStatus::NotInRepo,
+            confidence: Confidence::Low,
+            evidence: Vec::new(),
+            // Moved out of `dir_size` once it is no longer needed
+            dirs: Vec::new(),
+            size,
+            disk_size: 0,
+            reclaimable,
+            modified: sizing::to_system_time(dir_size.newest_mtime_ns),
+            project_modified: None,
+            link_target,
+            incomplete,
+        };
+        if !self.options.filters_by_age() {
+            self.observer.found(&item);
+        }
+        if let Ok(mut found_items) = self.found_items.lock() {
+            found_items.push((item, dir_size));
         }
     }
 }
 
-/// Scan directory recursively for dev dependencies using language cleaners
-fn scan_with_cleaners(root: &Path, cleaners: &[Box<dyn LanguageCleaner>]) -> Vec<FoundItem> {
-    let mut found_items = Vec::new();
-    let mut found_paths = std::collections::HashSet::new();
+/// Fill in each item's allocated size, counting every hardlinked file only
+/// for the first item (in path order) that contains one of its links
+fn finalize_disk_sizes(mut sized: Vec<(FoundItem, DirSize)>) -> Vec<FoundItem> {
+    sized.sort_by(|(a, _), (b, _)| a.path.cmp(&b.path));
 
-    for entry in WalkDir::new(root).into_iter().filter_map(|e| e.ok()) {
-        let path = entry.path();
+    let mut claimed = HashSet::new();
+    sized
+        .into_iter()
+        .map(|(mut item, dir_size)| {
+            item.disk_size = dir_size.allocated_size(&mut claimed);
+            item.dirs = dir_size.dirs;
+            item
+        })
+        .collect()
+}
+
+/// Record when each item's project was last worked on
+///
+/// Every project is walked once, however many artifacts it has, and all
+/// matched artifacts (reported or not) are left out of the walk, as is
+/// anything the scan didn't enter.
+fn add_project_activity(
+    items: &mut [FoundItem],
+    artifacts: &HashSet<PathBuf>,
+    exclude: &ExcludeSet,
+    mounts: &MountTable,
+    one_file_system: bool,
+) {
+    let mut locator = ProjectLocator::new();
+    let projects: Vec<PathBuf> = items
+        .iter()
+        .map(|item| locator.project_root(&item.path, &item.root))
+        .collect();
+
+    // Each project with the scan root it was found under
+    let unique: HashMap<&PathBuf, &Path> = projects
+        .iter()
+        .zip(items.iter())
+        .map(|(project, item)| (project, item.root.as_path()))
+        .collect();
+    let activity: HashMap<&PathBuf, u64> = unique
+        .into_par_iter()
+        .map(|(project, root)| {
+            let limits = project::WalkLimits {
+                skip: artifacts,
+                exclude,
+                mounts,
+                device: if one_file_system {
+                    fs::metadata(root).ok().and_then(|m| sizing::device(&m))
+                } else {
+                    None
+                },
+            };
+            (project, project::newest_source_mtime(project, &limits))
+        })
+        .collect();
+
+    for (item, project) in items.iter_mut().zip(&projects) {
+        item.project_modified = activity
+            .get(project)
+            .and_then(|&mtime_ns| sizing::to_system_time(mtime_ns));
+    }
+}
+
+/// A directory queued for a visit
+struct Visit {
+    /// Path as walked (the link itself for a followed symlink)
+    path: PathBuf,
+    /// Where `path` points to, when it is a followed symlink
+    link_target: Option<PathBuf>,
+    depth: usize,
+    root: Arc<ScanRoot>,
+    /// Nearest project root above the directory
+    project: Arc<PathBuf>,
+    /// Entry names of the parent directory; `None` for a scan root
+    siblings: Option<Arc<HashSet<OsString>>>,
+}
 
-        if !path.is_dir() {
+/// Visit a directory: size it if it matches, otherwise fan out into its
+/// subdirectories
+///
+/// The directory is listed once, and rules checking for marker files are
+/// answered from that listing and the parent's. Matched directories are
+/// sized instead of descended into, so nothing below an artifact is ever
+/// visited twice. Excluded subdirectories are dropped before they are read.
+fn visit_dir<'s>(scope: &rayon::Scope<'s>, visit: Visit, ctx: &'s ScanContext<'s>) {
+    let Visit {
+        path,
+        link_target,
+        depth,
+        root,
+        project,
+        siblings,
+    } = visit;
+    let dir = link_target.as_deref().unwrap_or(&path);
+
+    let entries: Result<Vec<fs::DirEntry>, _> = fs::read_dir(dir).map(|entries| {
+        entries
+            .filter_map(|entry| {
+                entry
+                    .map_err(|e| ctx.report_errors([ScanError::new(dir, &e)]))
+                    .ok()
+            })
+            .collect()
+    });
+    let names: HashSet<OsString> = match &entries {
+        Ok(entries) => entries.iter().map(|entry| entry.file_name()).collect(),
+        Err(_) => HashSet::new(),
+    };
+
+    let dir_name = path.file_name().and_then(|n| n.to_str());
+    let matched = dir_name.and_then(|name| {
+        let relative = match siblings {
+            Some(_) => path.strip_prefix(&*project).unwrap_or(&path),
+            // Nothing above a root is walked, so its parent is the project it
+            // belongs to
+            None => Path::new(name),
+        };
+        let candidate = Candidate {
+            name,
+            path: &path,
+            relative,
+            siblings: siblings.as_deref(),
+            contents: entries.is_ok().then_some(&names),
+        };
+        match_directory(&candidate, &ctx.rules)
+    });
+    if let Some(matched) = matched {
+        // Sizing reports the directory if it can't be read
+        ctx.record(path, &root.root, matched, link_target);
+        return;
+    }
+
+    let entries = match entries {
+        Ok(entries) => entries,
+        Err(e) => {
+            ctx.report_errors([ScanError::new(dir, &e)]);
+            return;
+        }
+    };
+    ctx.observer.dir_visited();
+
+    let hidden = dir_name.is_some_and(|name| name.starts_with('.'));
+    // A root is walked even if hidden; subdirectories deeper than the maximum
+    // depth are never visited
+    if siblings.is_some() && hidden && ctx.options.skip_hidden
+        || ctx.options.max_depth.is_some_and(|max| depth >= max)
+    {
+        return;
+    }
+
+    let (root, project) = match &link_target {
+        // Walk the link's target as if it were a root of its own
+        Some(target) => {
+            let base = Arc::new(ScanRoot {
+                root: root.root.clone(),
+                path: target.clone(),
+                canonical: target.clone(),
+                dev: root.dev,
+            });
+            (base, Arc::new(target.clone()))
+        }
+        None => (root, project),
+    };
+    // Path patterns are matched relative to the nearest project root
+    let project = if names.iter().any(|name| ctx.markers.contains(name)) {
+        Arc::new(dir.to_path_buf())
+    } else {
+        project
+    };
+
+    let names = Arc::new(names);
+    for entry in entries {
+        // DirEntry::file_type doesn't follow symlinks; links are only walked
+        // when the symlink policy allows it
+        let file_type = match entry.file_type() {
+            Ok(file_type) => file_type,
+            Err(e) => {
+                ctx.report_errors([ScanError::new(&entry.path(), &e)]);
+                continue;
+            }
+        };
+        if !file_type.is_dir() && !file_type.is_symlink() {
             continue;
         }
 
-        if let Some(dir_name) = path.file_name().and_then(|n| n.to_str()) {
-            // Try each cleaner's patterns
-            for cleaner in cleaners {
-                for pattern in cleaner.project_patterns() {
-                    if let Some((ecosystem, icon)) =
-                        check_pattern(dir_name, path, &pattern, &**cleaner)
-                    {
-                        // Check if this path is already inside a found item
-                        let is_nested = found_paths
-                            .iter()
-                            .any(|found_path: &PathBuf| path.starts_with(found_path));
-
-                        if !is_nested {
-                            let size = calculate_dir_size(path);
-
-                            if size > 0 {
-                                found_items.push(FoundItem {
-                                    path: path.to_path_buf(),
-                                    ecosystem,
-                                    icon,
-                                    size,
-                                });
-                                found_paths.insert(path.to_path_buf());
-                            }
-                        }
-                        break; // Found a match, no need to check other patterns
-                    }
+        let child = entry.path();
+        if ctx.exclude.is_excluded(&child) || ctx.set_aside.is_set_aside(&child) {
+            continue;
+        }
+
+        let link_target = if file_type.is_symlink() {
+            match ctx.follow_link(&child, &root) {
+                Some(target) if !ctx.set_aside.holds(&target) => Some(target),
+                _ => continue,
+            }
+        } else {
+            if let Some(skipped) = ctx.skipped_mount(&entry, &root) {
+                if let Ok(mut skipped_mounts) = ctx.skipped_mounts.lock() {
+                    skipped_mounts.push(skipped);
                 }
+                continue;
+            }
+            None
+        };
+
+        let visit = Visit {
+            path: child,
+            link_target,
+            depth: depth + 1,
+            root: Arc::clone(&root),
+            project: Arc::clone(&project),
+            siblings: Some(Arc::clone(&names)),
+        };
+        scope.spawn(move |s| visit_dir(s, visit, ctx));
+    }
+}
+
+/// Build the worker pool for a scan
+fn build_pool(jobs: usize) -> rayon::ThreadPool {
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(jobs)
+        .build()
+        .unwrap_or_else(|_| {
+            rayon::ThreadPoolBuilder::new()
+                .num_threads(1)
+                .build()
+                .expect("failed to start a scan worker thread")
+        })
+}
+
+/// Scan directories recursively for dev dependencies using language cleaners
+///
+/// Directory reads and sizing are fanned out across a worker pool of
+/// `options.jobs` threads. A matched directory is pruned from the walk, so
+/// each artifact is sized exactly once and nested matches (e.g. a
+/// `node_modules` inside another `node_modules`) are never reported.
+///
+/// Items are ordered by root (in the order given), then by size descending.
+fn scan_with_cleaners(
+    roots: &[PathBuf],
+    cleaners: &[Box<dyn LanguageCleaner>],
+    options: &ScanOptions,
+    observer: &dyn ScanObserver,
+) -> ScanReport {
+    // Build the pattern lists once instead of once per visited directory
+    let ctx = ScanContext {
+        rules: compile_patterns(cleaners),
+        markers: project::project_markers(),
+        found_items: Mutex::new(Vec::new()),
+        matched: Mutex::new(HashSet::new()),
+        skipped_mounts: Mutex::new(Vec::new()),
+        errors: Mutex::new(Vec::new()),
+        followed: Mutex::new(HashSet::new()),
+        cache: options
+            .use_cache
+            .then(SizeCache::default_path)
+            .flatten()
+            .map(SizeCache::load),
+        exclude: ExcludeSet::new(&options.exclude),
+        set_aside: SetAside::load(),
+        mounts: MountTable::load(),
+        size_options: SizeOptions {
+            one_file_system: options.one_file_system,
+            symlinks: options.symlinks,
+            roots: roots
+                .iter()
+                .filter_map(|r| fs::canonicalize(r).ok())
+                .collect(),
+        },
+        options,
+        observer,
+    };
+
+    let scan_roots: Vec<Arc<ScanRoot>> = roots
+        .iter()
+        .map(|root| ScanRoot {
+            root: root.clone(),
+            path: root.clone(),
+            canonical: fs::canonicalize(root).unwrap_or_else(|_| root.clone()),
+            dev: fs::metadata(root).ok().and_then(|m| sizing::device(&m)),
+        })
+        .filter(|root| {
+            let set_aside = ctx.set_aside.holds(&root.canonical);
+            if set_aside {
+                eprintln!(
+                    "⚠️  Skipping {}: it holds quarantined or trashed folders",
+                    root.root.display()
+                );
+            }
+            !set_aside
+        })
+        .map(Arc::new)
+        .collect();
+
+    let pool = build_pool(options.jobs);
+    pool.install(|| {
+        rayon::scope(|s| {
+            for root in &scan_roots {
+                let visit = Visit {
+                    path: root.path.clone(),
+                    link_target: None,
+                    depth: 0,
+                    root: Arc::clone(root),
+                    project: Arc::new(root.path.clone()),
+                    siblings: None,
+                };
+                let ctx = &ctx;
+                s.spawn(move |s| visit_dir(s, visit, ctx));
             }
+        })
+    });
+
+    if let Some(cache) = &ctx.cache {
+        if let Err(e) = cache.save() {
+            eprintln!("⚠️  Failed to save size cache: {}", e);
+        }
+    }
+
+    let mut found_items = finalize_disk_sizes(ctx.found_items.into_inner().unwrap_or_default());
+    let matched = ctx.matched.into_inner().unwrap_or_default();
+    pool.install(|| {
+        add_project_activity(
+            &mut found_items,
+            &matched,
+            &ctx.exclude,
+            &ctx.mounts,
+            options.one_file_system,
+        )
+    });
+
+    if options.filters_by_age() {
+        let now = SystemTime::now();
+        found_items.retain(|item| options.keeps(item, now));
+        for item in &found_items {
+            observer.found(item);
         }
     }
+    pool.install(|| confidence::assess(&mut found_items));
+
+    // Sort by root, then size descending, then path so the order never
+    // depends on which worker finished first
+    let root_index = |item: &FoundItem| roots.iter().position(|root| *root == item.root);
+    found_items.sort_by(|a, b| {
+        root_index(a)
+            .cmp(&root_index(b))
+            .then_with(|| b.size.cmp(&a.size))
+            .then_with(|| a.path.cmp(&b.path))
+    });
+
+    let mut skipped_mounts = ctx.skipped_mounts.into_inner().unwrap_or_default();
+    skipped_mounts.sort_by(|a, b| a.path.cmp(&b.path));
+
+    let mut errors = ctx.errors.into_inner().unwrap_or_default();
+    errors.sort_by(|a, b| a.path.cmp(&b.path));
+
+    ScanReport {
+        items: found_items,
+        skipped_mounts,
+        errors,
+    }
+}
 
-    // Sort by size descending
-    found_items.sort_by(|a, b| b.size.cmp(&a.size));
+/// Drop scan roots that don't exist or lie inside another root
+///
+/// Roots are compared by their canonical form, so `~/work` and
+/// `~/work/../work/app` are recognised as overlapping.
+pub fn dedup_roots(roots: &[PathBuf]) -> Vec<PathBuf> {
+    let mut canonical: Vec<(PathBuf, &PathBuf)> = Vec::new();
 
-    found_items
+    for root in roots {
+        match fs::canonicalize(root) {
+            Ok(path) => canonical.push((path, root)),
+            Err(e) => eprintln!("⚠️  Skipping {}: {}", root.display(), e),
+        }
+    }
+
+    canonical
+        .iter()
+        .enumerate()
+        .filter(|(idx, (path, root))| {
+            let covered_by = canonical
+                .iter()
+                .enumerate()
+                .find(|(other_idx, (other, _))| {
+                    // Among identical roots keep the first one given
+                    path.starts_with(other) && (path != other || other_idx < idx)
+                });
+            if let Some((_, (_, other_root))) = covered_by {
+                // On stderr, so `check --format json` output stays valid
+                eprintln!(
+                    "ℹ️  {} is already covered by {}",
+                    root.display(),
+                    other_root.display()
+                );
+            }
+            covered_by.is_none()
+        })
+        .map(|(_, (_, root))| (*root).clone())
+        .collect()
 }
 
-/// Scan directory recursively for all dev dependencies
-pub fn scan_directory(root: &Path) -> Vec<FoundItem> {
+/// Scan directories recursively for all dev dependencies
+pub fn scan_directory(
+    roots: &[PathBuf],
+    options: &ScanOptions,
+    observer: &dyn ScanObserver,
+) -> ScanReport {
     let cleaners = languages::get_all_cleaners();
-    scan_with_cleaners(root, &cleaners)
+    scan_with_cleaners(roots, &cleaners, options, observer)
 }
 
 /// Scan directory filtered by a specific language
-pub fn scan_directory_filtered(root: &Path, language: &str) -> Vec<FoundItem> {
+pub fn scan_directory_filtered(
+    roots: &[PathBuf],
+    language: &str,
+    options: &ScanOptions,
+    observer: &dyn ScanObserver,
+) -> ScanReport {
     match languages::get_cleaner_by_name(language) {
         Some(cleaner) => {
             let cleaners = vec![cleaner];
-            scan_with_cleaners(root, &cleaners)
+            scan_with_cleaners(roots, &cleaners, options, observer)
         }
         None => {
             let available: Vec<String> = languages::get_all_cleaners()
@@ -144,7 +803,94 @@ pub fn scan_directory_filtered(root: &Path, language: &str) -> Vec<FoundItem> {
                 language,
                 available.join(", ")
             );
-            Vec::new()
+            ScanReport::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(unix)]
+    use crate::testing::backdate;
+    use crate::testing::{self, test_dir};
+
+    fn venv(dir: &Path) {
+        fs::create_dir_all(dir.join("lib")).unwrap();
+        fs::write(dir.join("pyvenv.cfg"), "home = /usr/bin\n").unwrap();
+        fs::write(dir.join("lib/site.py"), "x = 1\n").unwrap();
+    }
+
+    fn found(root: &Path, options: &ScanOptions) -> Vec<PathBuf> {
+        testing::scan(root, options)
+            .into_iter()
+            .map(|item| item.path)
+            .collect()
+    }
+
+    #[test]
+    fn virtualenvs_without_pyvenv_cfg_are_found_by_their_interpreter() {
+        let root = test_dir("legacy-venv");
+        fs::create_dir_all(root.join("app/venv/bin")).unwrap();
+        fs::write(root.join("app/venv/bin/python"), "#!/bin/sh\n").unwrap();
+        // Named like a venv, but no interpreter
+        fs::create_dir_all(root.join("app/env/bin")).unwrap();
+        fs::write(root.join("app/env/bin/setup.sh"), "#!/bin/sh\n").unwrap();
+
+        assert_eq!(
+            found(&root, &ScanOptions::default()),
+            [root.join("app/venv")]
+        );
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn unreported_artifacts_dont_count_as_project_activity() {
+        let root = test_dir("activity");
+        let project = root.join("app");
+        fs::create_dir_all(project.join("src")).unwrap();
+        fs::create_dir_all(project.join("__pycache__")).unwrap();
+        fs::create_dir_all(project.join("target/debug")).unwrap();
+        fs::write(project.join("Cargo.toml"), "[package]\n").unwrap();
+        fs::write(project.join("src/main.py"), "print(1)\n").unwrap();
+        fs::write(project.join("__pycache__/main.pyc"), "bytecode").unwrap();
+        // Freshly built, but not what is asked for
+        fs::write(project.join("target/debug/app"), "binary").unwrap();
+        for old in [
+            "Cargo.toml",
+            "src/main.py",
+            "src",
+            "__pycache__/main.pyc",
+            "__pycache__",
+        ] {
+            backdate(&project.join(old), 100);
         }
+
+        let options = ScanOptions {
+            older_than: Some(Duration::from_secs(30 * 24 * 60 * 60)),
+            kinds: vec![ArtifactKind::Cache],
+            ..ScanOptions::default()
+        };
+        assert_eq!(found(&root, &options), [project.join("__pycache__")]);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn quarantined_and_trashed_folders_are_never_found() {
+        let root = test_dir("set-aside");
+        venv(&root.join("app/.venv"));
+        venv(&root.join(".sweepkit-quarantine-1000/2024-01-01T00-00-00/1-.venv"));
+        venv(&root.join(".Trash-1000/files/.venv"));
+        venv(&root.join(".Trash/1000/files/env"));
+
+        let options = ScanOptions::default();
+        assert_eq!(found(&root, &options), [root.join("app/.venv")]);
+        // Not even when scanned directly
+        assert!(found(&root.join(".Trash-1000/files"), &options).is_empty());
+
+        fs::remove_dir_all(&root).unwrap();
     }
 }
@@ -0,0 +1,444 @@
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fs::{self, Metadata};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Identity of a file on disk: (device, inode)
+pub type FileId = (u64, u64);
+
+/// A file with more than one hard link, seen while sizing a directory
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct LinkedFile {
+    pub nlink: u64,
+    pub apparent: u64,
+    pub allocated: u64,
+    /// How many of its links were found inside the sized directory
+    pub seen: u64,
+}
+
+/// A file or directory that could not be read during a scan
+#[derive(Debug, Clone)]
+pub struct ScanError {
+    pub path: PathBuf,
+    pub kind: io::ErrorKind,
+}
+
+impl ScanError {
+    pub fn new(path: &Path, error: &io::Error) -> ScanError {
+        ScanError {
+            path: path.to_path_buf(),
+            kind: error.kind(),
+        }
+    }
+}
+
+impl fmt::Display for ScanError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path.display(), self.kind)
+    }
+}
+
+/// Modification time and inode of one directory, used to tell whether a
+/// previously sized tree has changed since
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DirStamp {
+    pub path: PathBuf,
+    pub mtime_ns: u64,
+    pub inode: u64,
+}
+
+/// Modification time in nanoseconds since the Unix epoch (0 if unavailable)
+pub fn mtime_ns(metadata: &Metadata) -> u64 {
+    metadata
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or(0)
+}
+
+/// Convert a `mtime_ns` value back to a timestamp
+pub fn to_system_time(mtime_ns: u64) -> Option<SystemTime> {
+    (mtime_ns > 0).then(|| UNIX_EPOCH + Duration::from_nanos(mtime_ns))
+}
+
+impl DirStamp {
+    fn from_metadata(path: &Path, metadata: &Metadata) -> DirStamp {
+        DirStamp {
+            path: path.to_path_buf(),
+            mtime_ns: mtime_ns(metadata),
+            inode: inode(metadata),
+        }
+    }
+
+    /// Stamp a directory as it is on disk right now
+    pub fn read(path: &Path) -> Option<DirStamp> {
+        fs::symlink_metadata(path)
+            .ok()
+            .map(|metadata| DirStamp::from_metadata(path, &metadata))
+    }
+}
+
+/// Size of a directory tree, before cross-item hardlink accounting
+#[derive(Debug, Clone, Default)]
+pub struct DirSize {
+    /// Apparent bytes of files with a single link
+    pub apparent: u64,
+    /// Allocated bytes of single-link files and of the directories themselves
+    pub allocated: u64,
+    /// Hardlinked files, keyed by identity so each is counted once
+    pub linked: HashMap<FileId, LinkedFile>,
+    /// Every directory of the tree, so the result can be cached and revalidated
+    pub dirs: Vec<DirStamp>,
+    /// Newest modification time of any file or directory in the tree
+    pub newest_mtime_ns: u64,
+    /// Apparent bytes reached by following symlinks out of the tree; deleting
+    /// the tree only removes the links, so these are never reclaimable
+    pub via_symlinks: u64,
+    /// Entries that could not be read; the sizes above are undercounted
+    pub errors: Vec<ScanError>,
+}
+
+impl DirSize {
+    fn merge(mut self, mut other: DirSize) -> DirSize {
+        // Fold the smaller map into the larger one
+        if self.linked.len() < other.linked.len() {
+            std::mem::swap(&mut self.linked, &mut other.linked);
+        }
+        if self.dirs.len() < other.dirs.len() {
+            std::mem::swap(&mut self.dirs, &mut other.dirs);
+        }
+        self.dirs.append(&mut other.dirs);
+        self.errors.append(&mut other.errors);
+        self.newest_mtime_ns = self.newest_mtime_ns.max(other.newest_mtime_ns);
+        self.via_symlinks += other.via_symlinks;
+        self.apparent += other.apparent;
+        self.allocated += other.allocated;
+        for (id, file) in other.linked {
+            self.linked
+                .entry(id)
+                .and_modify(|existing| existing.seen += file.seen)
+                .or_insert(file);
+        }
+        self
+    }
+
+    /// Apparent size, counting each hardlinked file once
+    pub fn apparent_size(&self) -> u64 {
+        self.apparent + self.via_symlinks + self.linked.values().map(|f| f.apparent).sum::<u64>()
+    }
+
+    /// Bytes actually freed by deleting the tree: hardlinked files only count
+    /// when every one of their links lives inside it
+    pub fn reclaimable_size(&self) -> u64 {
+        self.allocated
+            + self
+                .linked
+                .values()
+                .filter(|f| f.seen >= f.nlink)
+                .map(|f| f.allocated)
+                .sum::<u64>()
+    }
+
+    /// Allocated size, counting only hardlinked files not already claimed by
+    /// another item of the same scan
+    pub fn allocated_size(&self, claimed: &mut HashSet<FileId>) -> u64 {
+        self.allocated
+            + self
+                .linked
+                .iter()
+                .filter(|(id, _)| claimed.insert(**id))
+                .map(|(_, f)| f.allocated)
+                .sum::<u64>()
+    }
+}
+
+/// Bytes allocated on disk for a file
+#[cfg(unix)]
+pub fn allocated_len(metadata: &Metadata) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+    // st_blocks is always in 512-byte units, whatever the filesystem block size
+    metadata.blocks() * 512
+}
+
+#[cfg(not(unix))]
+pub fn allocated_len(metadata: &Metadata) -> u64 {
+    metadata.len()
+}
+
+/// Inode number of a file (0 where the platform has none)
+#[cfg(unix)]
+fn inode(metadata: &Metadata) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+    metadata.ino()
+}
+
+#[cfg(not(unix))]
+fn inode(_metadata: &Metadata) -> u64 {
+    0
+}
+
+/// Device a file lives on, if the platform exposes one
+#[cfg(unix)]
+pub fn device(metadata: &Metadata) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+    Some(metadata.dev())
+}
+
+#[cfg(not(unix))]
+pub fn device(_metadata: &Metadata) -> Option<u64> {
+    None
+}
+
+/// Identity and link count of a file, if it has more than one link
+#[cfg(unix)]
+fn hardlink_id(metadata: &Metadata) -> Option<(FileId, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    if metadata.nlink() > 1 {
+        Some(((metadata.dev(), metadata.ino()), metadata.nlink()))
+    } else {
+        None
+    }
+}
+
+#[cfg(not(unix))]
+fn hardlink_id(_metadata: &Metadata) -> Option<(FileId, u64)> {
+    None
+}
+
+/// Which symbolic links to directories are followed
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+    clap::ValueEnum,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum SymlinkPolicy {
+    /// Never follow symlinks
+    #[default]
+    Never,
+    /// Follow symlinks whose target is inside one of the scan roots
+    WithinRoot,
+    /// Follow every symlink
+    Always,
+}
+
+/// Options controlling how a tree is sized
+#[derive(Debug, Clone, Default)]
+pub struct SizeOptions {
+    /// Don't count subdirectories on another filesystem than the sized directory
+    pub one_file_system: bool,
+    pub symlinks: SymlinkPolicy,
+    /// Canonical scan roots, for `SymlinkPolicy::WithinRoot`
+    pub roots: Vec<PathBuf>,
+}
+
+impl SizeOptions {
+    /// Resolve a symlink and decide whether to follow it
+    ///
+    /// Returns the canonical target directory, or None when the policy forbids
+    /// following it or the target is inside `covered` (an area that is walked
+    /// anyway). Links back to an ancestor always point into a covered area, so
+    /// this also breaks symlink cycles.
+    pub fn follow(&self, link: &Path, covered: &[&Path]) -> Option<PathBuf> {
+        if self.symlinks == SymlinkPolicy::Never {
+            return None;
+        }
+
+        let target = fs::canonicalize(link).ok()?;
+        if !target.is_dir() || covered.iter().any(|area| target.starts_with(area)) {
+            return None;
+        }
+        if self.symlinks == SymlinkPolicy::WithinRoot
+            && !self.roots.iter().any(|root| target.starts_with(root))
+        {
+            return None;
+        }
+
+        Some(target)
+    }
+}
+
+/// Per-call state of a sizing walk
+struct SizeWalk<'a> {
+    options: &'a SizeOptions,
+    root_dev: Option<u64>,
+    /// Canonical path of the sized directory
+    area: PathBuf,
+    /// Symlink targets already counted, so two links to one place count once
+    followed: Mutex<HashSet<PathBuf>>,
+}
+
+/// Calculate directory size recursively
+///
+/// Subdirectories are sized in parallel on the current rayon pool.
+pub fn calculate_dir_size(path: &Path, options: &SizeOptions) -> DirSize {
+    let walk = SizeWalk {
+        options,
+        root_dev: fs::symlink_metadata(path)
+            .ok()
+            .and_then(|metadata| device(&metadata)),
+        area: fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf()),
+        followed: Mutex::new(HashSet::new()),
+    };
+    size_tree(path, &walk)
+}
+
+fn size_tree(path: &Path, walk: &SizeWalk) -> DirSize {
+    let mut size = DirSize::default();
+
+    match fs::symlink_metadata(path) {
+        Ok(metadata) => {
+            if walk.options.one_file_system && device(&metadata) != walk.root_dev {
+                return size;
+            }
+            size.allocated += allocated_len(&metadata);
+            size.newest_mtime_ns = mtime_ns(&metadata);
+            size.dirs.push(DirStamp::from_metadata(path, &metadata));
+        }
+        Err(e) => {
+            size.errors.push(ScanError::new(path, &e));
+            return size;
+        }
+    }
+
+    let entries = match fs::read_dir(path) {
+        Ok(entries) => entries,
+        Err(e) => {
+            size.errors.push(ScanError::new(path, &e));
+            return size;
+        }
+    };
+
+    let mut subdirs = Vec::new();
+
+    for entry in entries {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                size.errors.push(ScanError::new(path, &e));
+                continue;
+            }
+        };
+        let file_type = match entry.file_type() {
+            Ok(file_type) => file_type,
+            Err(e) => {
+                size.errors.push(ScanError::new(&entry.path(), &e));
+                continue;
+            }
+        };
+
+        if file_type.is_dir() {
+            subdirs.push(entry.path());
+        } else if file_type.is_symlink() {
+            let Some(target) = walk.options.follow(&entry.path(), &[&walk.area]) else {
+                continue;
+            };
+            let first_visit = walk
+                .followed
+                .lock()
+                .map(|mut followed| followed.insert(target.clone()))
+                .unwrap_or(false);
+            if first_visit {
+                let linked = size_tree(&target, walk);
+                size.via_symlinks += linked.apparent_size();
+                size.newest_mtime_ns = size.newest_mtime_ns.max(linked.newest_mtime_ns);
+                // Keep the target's stamps: they lie outside the tree, which
+                // also keeps this result out of the size cache
+                size.dirs.extend(linked.dirs);
+                size.errors.extend(linked.errors);
+            }
+        } else if file_type.is_file() {
+            let metadata = match entry.metadata() {
+                Ok(metadata) => metadata,
+                Err(e) => {
+                    size.errors.push(ScanError::new(&entry.path(), &e));
+                    continue;
+                }
+            };
+            size.newest_mtime_ns = size.newest_mtime_ns.max(mtime_ns(&metadata));
+
+            match hardlink_id(&metadata) {
+                Some((id, nlink)) => {
+                    size.linked
+                        .entry(id)
+                        .and_modify(|file| file.seen += 1)
+                        .or_insert(LinkedFile {
+                            nlink,
+                            apparent: metadata.len(),
+                            allocated: allocated_len(&metadata),
+                            seen: 1,
+                        });
+                }
+                None => {
+                    size.apparent += metadata.len();
+                    size.allocated += allocated_len(&metadata);
+                }
+            }
+        }
+    }
+
+    let nested = subdirs
+        .par_iter()
+        .map(|dir| size_tree(dir, walk))
+        .reduce(DirSize::default, DirSize::merge);
+
+    size.merge(nested)
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::testing::test_dir;
+
+    fn allocated(path: &Path) -> u64 {
+        allocated_len(&fs::symlink_metadata(path).unwrap())
+    }
+
+    #[test]
+    fn hardlinked_files_count_once_and_only_free_space_when_all_links_go() {
+        let root = test_dir("hardlinks");
+        let tree = root.join("target");
+        let other = root.join("other/target");
+        fs::create_dir_all(&tree).unwrap();
+        fs::create_dir_all(&other).unwrap();
+        fs::write(tree.join("own"), vec![1; 1_000]).unwrap();
+        // Both links inside the tree
+        fs::write(tree.join("inside"), vec![2; 20_000]).unwrap();
+        fs::hard_link(tree.join("inside"), tree.join("inside-again")).unwrap();
+        // One link in each tree
+        fs::write(root.join("shared"), vec![3; 300_000]).unwrap();
+        fs::hard_link(root.join("shared"), tree.join("shared")).unwrap();
+        fs::hard_link(root.join("shared"), other.join("shared")).unwrap();
+
+        let options = SizeOptions::default();
+        let size = calculate_dir_size(&tree, &options);
+        assert!(size.errors.is_empty());
+        assert_eq!(size.apparent_size(), 1_000 + 20_000 + 300_000);
+
+        let freed =
+            allocated(&tree) + allocated(&tree.join("own")) + allocated(&tree.join("inside"));
+        assert_eq!(size.reclaimable_size(), freed);
+
+        // On disk, a file shared by two found trees belongs to the first
+        let mut claimed = HashSet::new();
+        let shared = allocated(&root.join("shared"));
+        assert_eq!(size.allocated_size(&mut claimed), freed + shared);
+        let other_size = calculate_dir_size(&other, &options);
+        assert_eq!(other_size.reclaimable_size(), allocated(&other));
+        assert_eq!(other_size.allocated_size(&mut claimed), allocated(&other));
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,55 @@
+//! Helpers shared by the unit tests
+
+use crate::scanner::{self, FoundItem, ScanObserver, ScanOptions};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Scan observer that reports nothing
+pub struct Quiet;
+
+impl ScanObserver for Quiet {}
+
+/// An empty directory for one test
+pub fn test_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("sweepkit-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Items found below `root`, in path order
+pub fn scan(root: &Path, options: &ScanOptions) -> Vec<FoundItem> {
+    let mut items = scanner::scan_directory(&[root.to_path_buf()], options, &Quiet).items;
+    items.sort_by(|a, b| a.path.cmp(&b.path));
+    items
+}
+
+/// Set the modification time of a file or directory `days` back
+///
+/// Timestamps only move in clock ticks: backdating a tree makes sure a change
+/// right after it shows.
+#[cfg(unix)]
+pub fn backdate(path: &Path, days: u64) {
+    use std::os::unix::ffi::OsStrExt;
+
+    let path = std::ffi::CString::new(path.as_os_str().as_bytes()).unwrap();
+    let time = libc::timespec {
+        tv_sec: (crate::utils::unix_now() - days * 24 * 60 * 60) as libc::time_t,
+        tv_nsec: 0,
+    };
+    let times = [time, time];
+    let set = unsafe { libc::utimensat(libc::AT_FDCWD, path.as_ptr(), times.as_ptr(), 0) };
+    assert_eq!(set, 0, "can't backdate {:?}", path);
+}
+
+/// Backdate every directory of a tree
+#[cfg(unix)]
+pub fn backdate_dirs(dir: &Path, days: u64) {
+    for entry in fs::read_dir(dir).unwrap() {
+        let entry = entry.unwrap();
+        if entry.file_type().unwrap().is_dir() {
+            backdate_dirs(&entry.path(), days);
+        }
+    }
+    backdate(dir, days);
+}
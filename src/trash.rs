@@ -0,0 +1,258 @@
+use crate::sizing;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Move a file or directory to the freedesktop.org Trash
+///
+/// The home trash (`~/.local/share/Trash`) is used when it is on the same
+/// filesystem as `path`, otherwise the `.Trash/$uid` or `.Trash-$uid`
+/// directory at the top of the item's own filesystem. Items are only ever
+/// renamed, never copied, so a trash on another filesystem is an error.
+/// Returns the trash directory the item went to.
+///
+/// Not available on macOS: its data directory is `~/Library/Application
+/// Support`, and a `Trash` there would never show up in Finder.
+#[cfg(all(unix, not(target_os = "macos")))]
+pub fn trash(path: &Path) -> io::Result<PathBuf> {
+    use std::os::unix::fs::MetadataExt;
+
+    let path = crate::utils::absolute_keep_link(path)?;
+    let name = path.file_name().unwrap_or_default();
+    let device = fs::symlink_metadata(&path)?.dev();
+
+    let home_trash = dirs::data_dir().map(|dir| dir.join("Trash"));
+    let (trash_dir, original) = match home_trash {
+        Some(trash) if device_of_nearest(&trash) == Some(device) => (trash, path.clone()),
+        _ => {
+            let top = top_dir(&path, device);
+            let trash = top_trash(&top).map_err(|e| {
+                other(format!(
+                    "{} is on another filesystem than your home trash, and there is no \
+                     usable trash at the top of its own ({}): {}",
+                    path.display(),
+                    top.display(),
+                    e
+                ))
+            })?;
+            // Trashes at the top of a filesystem record paths relative to it
+            let relative = path.strip_prefix(&top).unwrap_or(&path).to_path_buf();
+            (trash, relative)
+        }
+    };
+
+    let files = trash_dir.join("files");
+    let info = trash_dir.join("info");
+    create_private_dir(&files)?;
+    create_private_dir(&info)?;
+
+    let (trashed, info_file) = reserve_name(&files, &info, &original, name)?;
+    if let Err(e) = fs::rename(&path, &trashed) {
+        let _ = fs::remove_file(&info_file);
+        return Err(if e.raw_os_error() == Some(libc::EXDEV) {
+            other(format!(
+                "{} can't be moved to {} without copying (different mounts)",
+                path.display(),
+                trash_dir.display()
+            ))
+        } else {
+            e
+        });
+    }
+
+    Ok(trash_dir)
+}
+
+#[cfg(any(not(unix), target_os = "macos"))]
+pub fn trash(_path: &Path) -> io::Result<PathBuf> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "the trash is only supported on Linux and other freedesktop.org systems",
+    ))
+}
+
+#[cfg(unix)]
+fn other(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::Other, message)
+}
+
+/// Device of a path, or of its closest existing ancestor (the home trash may
+/// not exist yet)
+pub fn device_of_nearest(path: &Path) -> Option<u64> {
+    path.ancestors()
+        .find_map(|dir| fs::metadata(dir).ok())
+        .and_then(|metadata| sizing::device(&metadata))
+}
+
+/// Mount point of the filesystem holding `path`: its highest ancestor still
+/// on `device`
+pub fn top_dir(path: &Path, device: u64) -> PathBuf {
+    path.ancestors()
+        .skip(1)
+        .take_while(|dir| {
+            fs::metadata(dir).is_ok_and(|metadata| sizing::device(&metadata) == Some(device))
+        })
+        .last()
+        .unwrap_or(path)
+        .to_path_buf()
+}
+
+/// The trash directory at the top of a filesystem
+///
+/// An admin-created `$top/.Trash` (sticky, not a symlink) holds one
+/// directory per user; otherwise each user gets `$top/.Trash-$uid`.
+#[cfg(all(unix, not(target_os = "macos")))]
+fn top_trash(top: &Path) -> io::Result<PathBuf> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let uid = crate::utils::uid();
+    let shared = top.join(".Trash");
+    if let Ok(metadata) = fs::symlink_metadata(&shared) {
+        let sticky = metadata.permissions().mode() & 0o1000 != 0;
+        if metadata.is_dir() && sticky {
+            let trash = shared.join(uid.to_string());
+            if create_private_dir(&trash).is_ok() {
+                return Ok(trash);
+            }
+        }
+    }
+
+    let trash = top.join(format!(".Trash-{}", uid));
+    create_owned_dir(&trash)?;
+    Ok(trash)
+}
+
+/// Create a directory (and its parents) only its owner can enter
+pub fn create_private_dir(dir: &Path) -> io::Result<()> {
+    let mut builder = fs::DirBuilder::new();
+    builder.recursive(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::DirBuilderExt;
+        builder.mode(0o700);
+    }
+    match builder.create(dir) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == io::ErrorKind::AlreadyExists && dir.is_dir() => Ok(()),
+        Err(e) => Err(e),
+    }
+}
+
+/// Create a directory only its owner can enter, making sure an existing one
+/// is a real directory (not a symlink) of the current user
+///
+/// For per-user directories in shared places (`/mnt/usb/.Trash-1000`),
+/// which anyone could have created first. An existing one of the user's with
+/// wider permissions is narrowed to 0700.
+#[cfg(unix)]
+pub fn create_owned_dir(dir: &Path) -> io::Result<()> {
+    use std::os::unix::fs::{MetadataExt, PermissionsExt};
+
+    create_private_dir(dir)?;
+    let metadata = fs::symlink_metadata(dir)?;
+    if !metadata.is_dir() || metadata.uid() != crate::utils::uid() {
+        return Err(other(format!(
+            "{} isn't a directory of yours",
+            dir.display()
+        )));
+    }
+    if metadata.permissions().mode() & 0o077 != 0 {
+        fs::set_permissions(dir, fs::Permissions::from_mode(0o700))?;
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn create_owned_dir(dir: &Path) -> io::Result<()> {
+    create_private_dir(dir)
+}
+
+/// Pick a free name in the trash and claim it by creating its `.trashinfo`
+///
+/// The info file is created exclusively, so two processes trashing items of
+/// the same name never pick the same slot.
+#[cfg(all(unix, not(target_os = "macos")))]
+fn reserve_name(
+    files: &Path,
+    info: &Path,
+    original: &Path,
+    name: &std::ffi::OsStr,
+) -> io::Result<(PathBuf, PathBuf)> {
+    use std::io::Write;
+
+    let contents = format!(
+        "[Trash Info]\nPath={}\nDeletionDate={}\n",
+        percent_encode(original),
+        crate::utils::local_timestamp()
+    );
+
+    for n in 1.. {
+        let mut candidate = name.to_os_string();
+        if n > 1 {
+            candidate.push(format!(".{}", n));
+        }
+        let trashed = files.join(&candidate);
+        if fs::symlink_metadata(&trashed).is_ok() {
+            continue;
+        }
+
+        candidate.push(".trashinfo");
+        let info_file = info.join(&candidate);
+        match fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&info_file)
+        {
+            Ok(mut file) => {
+                if let Err(e) = file.write_all(contents.as_bytes()) {
+                    let _ = fs::remove_file(&info_file);
+                    return Err(e);
+                }
+                return Ok((trashed, info_file));
+            }
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    unreachable!()
+}
+
+/// Encode a path the way `.trashinfo` files expect (RFC 2396 escaping,
+/// keeping the `/` separators)
+#[cfg(all(unix, not(target_os = "macos")))]
+fn percent_encode(path: &Path) -> String {
+    use std::os::unix::ffi::OsStrExt;
+
+    let mut encoded = String::new();
+    for &byte in path.as_os_str().as_bytes() {
+        if byte.is_ascii_alphanumeric() || b"/-_.~".contains(&byte) {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
+
+#[cfg(all(test, unix, not(target_os = "macos")))]
+mod tests {
+    use super::*;
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+
+    #[test]
+    fn trashinfo_paths_are_percent_encoded() {
+        assert_eq!(
+            percent_encode(Path::new("/home/me/web-app/node_modules")),
+            "/home/me/web-app/node_modules"
+        );
+        assert_eq!(
+            percent_encode(Path::new("/tmp/my dir/50%#1")),
+            "/tmp/my%20dir/50%25%231"
+        );
+        assert_eq!(percent_encode(Path::new("/tmp/café")), "/tmp/caf%C3%A9");
+        // Not UTF-8: encoded byte by byte
+        let path = Path::new(OsStr::from_bytes(b"/tmp/\xff"));
+        assert_eq!(percent_encode(path), "/tmp/%FF");
+    }
+}
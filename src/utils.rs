@@ -1,43 +1,373 @@
-use crate::scanner::FoundItem;
+use crate::confidence::Confidence;
+use crate::git::GitStatus;
+use crate::mounts::SkippedMount;
+use crate::quarantine::Run;
+use crate::scanner::{FoundItem, ScanObserver};
+use crate::sizing::ScanError;
 use bytesize::ByteSize;
 use colored::Colorize;
+use indicatif::{ProgressBar, ProgressStyle};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 /// Format byte size to human-readable string
 pub fn format_size(bytes: u64) -> String {
     ByteSize::b(bytes).to_string()
 }
 
+/// Parse a human-readable size such as "100MB" or "1.5 GiB" (used as a clap value parser)
+pub fn parse_size(value: &str) -> Result<u64, String> {
+    value.trim().parse::<ByteSize>().map(|size| size.as_u64())
+}
+
+/// Format how long ago a timestamp was, e.g. "3d ago"
+pub fn format_age(time: Option<SystemTime>) -> String {
+    let Some(time) = time else {
+        return "never".to_string();
+    };
+
+    let secs = SystemTime::now()
+        .duration_since(time)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+
+    match secs {
+        0..=59 => "just now".to_string(),
+        60..=3599 => format!("{}m ago", secs / 60),
+        3600..=86_399 => format!("{}h ago", secs / 3600),
+        86_400..=2_591_999 => format!("{}d ago", secs / 86_400),
+        2_592_000..=31_535_999 => format!("{}mo ago", secs / 2_592_000),
+        _ => format!("{}y ago", secs / 31_536_000),
+    }
+}
+
+/// Absolute path of a file or directory, resolving symlinks in its parent
+/// only, so a symlinked artifact stays the link itself
+pub fn absolute_keep_link(path: &Path) -> io::Result<PathBuf> {
+    let name = path
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "no file name"))?;
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    Ok(fs::canonicalize(parent)?.join(name))
+}
+
+/// Seconds since the Unix epoch
+pub fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Real user id of the process
+#[cfg(unix)]
+pub fn uid() -> u32 {
+    unsafe { libc::getuid() }
+}
+
+#[cfg(not(unix))]
+pub fn uid() -> u32 {
+    0
+}
+
+/// Current local time as `YYYY-MM-DDThh:mm:ss`
+#[cfg(unix)]
+pub fn local_timestamp() -> String {
+    let now = unsafe { libc::time(std::ptr::null_mut()) };
+    let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+    if unsafe { libc::localtime_r(&now, &mut tm) }.is_null() {
+        return String::new();
+    }
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}",
+        tm.tm_year + 1900,
+        tm.tm_mon + 1,
+        tm.tm_mday,
+        tm.tm_hour,
+        tm.tm_min,
+        tm.tm_sec
+    )
+}
+
+#[cfg(not(unix))]
+pub fn local_timestamp() -> String {
+    // No portable way to get the local offset; seconds since the epoch will do
+    unix_now().to_string()
+}
+
 /// Get color for ecosystem type
 fn get_ecosystem_color(ecosystem: &str) -> colored::Color {
     match ecosystem {
-        "Node.js" | "Next.js" | "JavaScript" | "Parcel" | "Bower" => colored::Color::Yellow,
-        "Python" | "Python venv" | "Python tox" | "Python pytest" | "Python mypy" => {
-            colored::Color::Blue
+        "Node.js" | "Next.js" | "Nuxt" | "JavaScript" | "Parcel" | "Bower" | "Yarn" => {
+            colored::Color::Yellow
         }
+        "Python" | "Python venv" | "Python tox" | "Python pytest" | "Python mypy"
+        | "Python ruff" | "Python egg-info" | "Python dist" => colored::Color::Blue,
         "Rust" => colored::Color::Red,
-        "Gradle" | "CMake" => colored::Color::Green,
+        "Gradle" | "Java" | "CMake" | "C++" => colored::Color::Green,
         _ => colored::Color::White,
     }
 }
 
+/// Live progress line for a running scan
+///
+/// Prints each item as soon as it is known to be reported, and keeps a
+/// spinner with the running totals and the directories still being sized
+/// below them.
+pub struct ScanProgress {
+    bar: ProgressBar,
+    dirs: AtomicU64,
+    items: AtomicU64,
+    bytes: AtomicU64,
+    sizing: Mutex<BTreeSet<PathBuf>>,
+}
+
+impl ScanProgress {
+    pub fn new() -> ScanProgress {
+        // Drawn on stderr, and hidden entirely when it is not a terminal
+        let bar = ProgressBar::new_spinner();
+        bar.set_style(ProgressStyle::with_template("{spinner:.cyan} {msg}").unwrap());
+        bar.enable_steady_tick(Duration::from_millis(100));
+
+        ScanProgress {
+            bar,
+            dirs: AtomicU64::new(0),
+            items: AtomicU64::new(0),
+            bytes: AtomicU64::new(0),
+            sizing: Mutex::new(BTreeSet::new()),
+        }
+    }
+
+    fn refresh(&self) {
+        let mut message = format!(
+            "{} directories visited, {} found, {}",
+            self.dirs.load(Ordering::Relaxed),
+            self.items.load(Ordering::Relaxed),
+            format_size(self.bytes.load(Ordering::Relaxed))
+        );
+        if let Ok(sizing) = self.sizing.lock() {
+            if let Some(first) = sizing.iter().next() {
+                message.push_str(&format!(" - sizing {}", first.display()));
+                if sizing.len() > 1 {
+                    message.push_str(&format!(" (+{} more)", sizing.len() - 1));
+                }
+            }
+        }
+        self.bar.set_message(message);
+    }
+
+    /// Remove the progress line once the scan is done
+    pub fn finish(&self) {
+        self.bar.finish_and_clear();
+    }
+}
+
+impl ScanObserver for ScanProgress {
+    fn dir_visited(&self) {
+        // Formatting on every directory would cost more than reading it
+        if self.dirs.fetch_add(1, Ordering::Relaxed) % 256 == 0 {
+            self.refresh();
+        }
+    }
+
+    fn matched(&self, path: &Path, _ecosystem: &str, _icon: &str) {
+        if let Ok(mut sizing) = self.sizing.lock() {
+            sizing.insert(path.to_path_buf());
+        }
+        self.refresh();
+    }
+
+    fn sized(&self, path: &Path) {
+        if let Ok(mut sizing) = self.sizing.lock() {
+            sizing.remove(path);
+        }
+        self.refresh();
+    }
+
+    fn found(&self, item: &FoundItem) {
+        self.items.fetch_add(1, Ordering::Relaxed);
+        self.bytes.fetch_add(item.size, Ordering::Relaxed);
+        let label = format!("{} {}", item.icon, item.label)
+            .color(get_ecosystem_color(&item.label))
+            .bold();
+        self.bar.println(format!(
+            "  {} {} - {}",
+            label,
+            format_size(item.size).cyan(),
+            item.path.display().to_string().dimmed()
+        ));
+        self.refresh();
+    }
+}
+
+/// Summarize the mount points a scan stayed out of
+pub fn display_skipped_mounts(skipped: &[SkippedMount]) {
+    if skipped.is_empty() {
+        return;
+    }
+
+    println!(
+        "{}  Skipped {} mount point(s):",
+        "⏭️".bold(),
+        skipped.len().to_string().bold()
+    );
+    for mount in skipped {
+        println!(
+            "  • {} ({}, {})",
+            mount.path.display(),
+            mount.fs_type,
+            mount.reason.to_string().dimmed()
+        );
+    }
+    println!();
+}
+
+/// Summarize the entries a scan could not read, listing the first few
+pub fn display_scan_errors(errors: &[ScanError]) {
+    const SHOWN: usize = 10;
+
+    if errors.is_empty() {
+        return;
+    }
+
+    let mut by_kind: BTreeMap<String, usize> = BTreeMap::new();
+    for error in errors {
+        *by_kind.entry(error.kind.to_string()).or_default() += 1;
+    }
+    let kinds: Vec<String> = by_kind
+        .iter()
+        .map(|(kind, count)| format!("{} {}", count, kind))
+        .collect();
+
+    eprintln!(
+        "{}  {} entries could not be read ({}); sizes may be undercounted:",
+        "⚠️".bold(),
+        errors.len().to_string().bold(),
+        kinds.join(", ")
+    );
+    for error in errors.iter().take(SHOWN) {
+        eprintln!("  • {}", error.to_string().dimmed());
+    }
+    if errors.len() > SHOWN {
+        eprintln!("  ... and {} more", errors.len() - SHOWN);
+    }
+    eprintln!();
+}
+
 /// Display scan results in a formatted table
 pub fn display_scan_results(items: &[FoundItem]) {
     println!("{}", "Found dev dependency folders:".bold());
     println!("{}", "━".repeat(80).dimmed());
 
+    // Items arrive grouped by root; only label the groups when there are several
+    let grouped = items.iter().any(|item| item.root != items[0].root);
+
     let mut total_size: u64 = 0;
+    let mut total_disk: u64 = 0;
+    let mut total_reclaimable: u64 = 0;
+    let mut start = 0;
 
-    for (idx, item) in items.iter().enumerate() {
-        let ecosystem_display = format!("{} {}", item.icon, item.ecosystem);
-        let ecosystem = ecosystem_display
-            .color(get_ecosystem_color(&item.ecosystem))
-            .bold();
-        let size = format_size(item.size).cyan().bold();
-        let path = item.path.display().to_string().dimmed();
+    while start < items.len() {
+        let root = &items[start].root;
+        let end = items[start..]
+            .iter()
+            .position(|item| item.root != *root)
+            .map_or(items.len(), |len| start + len);
+        let group = &items[start..end];
+
+        if grouped {
+            println!("{} {}", "📁".bold(), root.display().to_string().bold());
+        }
 
-        println!("{:3}. {} {} - {}", idx + 1, ecosystem, size, path);
+        for (idx, item) in group.iter().enumerate() {
+            let label_display = format!("{} {}", item.icon, item.label);
+            let label = label_display.color(get_ecosystem_color(&item.label)).bold();
+            let size = if item.incomplete {
+                format!("{} (size incomplete)", format_size(item.size))
+                    .red()
+                    .bold()
+            } else {
+                format_size(item.size).cyan().bold()
+            };
+            let frees = format!("(frees {})", format_size(item.reclaimable)).green();
+            let active = format!("[active {}]", format_age(item.last_active())).yellow();
+            let mut path = match &item.link_target {
+                Some(target) => format!("{} → {}", item.path.display(), target.display()),
+                None => item.path.display().to_string(),
+            };
+            if let Some(details) = &item.details {
+                path.push_str(&format!(" ({})", details));
+            }
+            let path = path.dimmed();
+            // Name the ecosystem when the label doesn't already
+            let mut kind = if item.label.starts_with(&item.ecosystem) {
+                item.kind.to_string()
+            } else {
+                format!("{} {}", item.ecosystem, item.kind)
+            };
+            if let Some(command) = &item.regenerate {
+                kind.push_str(&format!(", `{}` to regenerate", command));
+            }
+            let mut kind = kind.dimmed().to_string();
+            match item.git {
+                GitStatus::NotInRepo => {}
+                GitStatus::Tracked(_) | GitStatus::Unknown => {
+                    kind.push_str(&format!("{} {}", ",".dimmed(), item.git.to_string().red()))
+                }
+                _ => kind.push_str(&format!(", {}", item.git).dimmed().to_string()),
+            }
+            // Only worth a mention when the pattern alone wasn't conclusive
+            if item.ambiguous || item.confidence != Confidence::High {
+                let mut confidence = format!("{} confidence", item.confidence);
+                if !item.evidence.is_empty() {
+                    let evidence: Vec<String> =
+                        item.evidence.iter().map(|e| e.to_string()).collect();
+                    confidence.push_str(&format!(" ({})", evidence.join(", ")));
+                }
+                let confidence = match item.confidence {
+                    Confidence::Low => confidence.red(),
+                    Confidence::Medium => confidence.yellow(),
+                    Confidence::High => confidence.green(),
+                };
+                kind.push_str(&format!("{} {}", ",".dimmed(), confidence));
+            }
 
-        total_size += item.size;
+            println!(
+                "{:3}. {} {} {} {} - {}",
+                start + idx + 1,
+                label,
+                size,
+                frees,
+                active,
+                path
+            );
+            println!("       {}", kind);
+        }
+
+        let group_size: u64 = group.iter().map(|item| item.size).sum();
+        let group_reclaimable: u64 = group.iter().map(|item| item.reclaimable).sum();
+        if grouped {
+            println!(
+                "     {} {} directories, {} (frees {})\n",
+                "└".dimmed(),
+                group.len(),
+                format_size(group_size).bold(),
+                format_size(group_reclaimable).green()
+            );
+        }
+
+        total_size += group_size;
+        total_disk += group.iter().map(|item| item.disk_size).sum::<u64>();
+        total_reclaimable += group_reclaimable;
+        start = end;
     }
 
     println!("{}", "━".repeat(80).dimmed());
@@ -47,8 +377,41 @@ pub fn display_scan_results(items: &[FoundItem]) {
         items.len().to_string().bold()
     );
     println!(
-        "{}  Total size: {}",
+        "{}  Total size: {} ({} on disk)",
         "💾".bold(),
-        format_size(total_size).bold().green()
+        format_size(total_size).bold(),
+        format_size(total_disk)
+    );
+    println!(
+        "{}  Reclaimable: {}",
+        "🧹".bold(),
+        format_size(total_reclaimable).bold().green()
+    );
+}
+
+/// Display quarantined runs, oldest first
+pub fn display_quarantine(runs: &[Run]) {
+    if runs.is_empty() {
+        println!("✨ The quarantine is empty");
+        return;
+    }
+
+    for run in runs {
+        println!(
+            "📦 {} - {} directories, {} [{}]",
+            run.id.bold(),
+            run.entries.len(),
+            format_size(run.size()),
+            format_age(Some(run.created_at())).yellow()
+        );
+        for entry in &run.entries {
+            println!("     {}", entry.original.display().to_string().dimmed());
+        }
+    }
+
+    let total: u64 = runs.iter().map(Run::size).sum();
+    println!(
+        "\n💾 Purging everything would reclaim {}",
+        format_size(total)
     );
 }